@@ -0,0 +1,361 @@
+use crate::model::{Citation, ConversationItem, MessageMetadata};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::{ops::Range, sync::OnceLock};
+
+/// Delimiters of the footnote references inserted by `cite_sources` until `number_citations`
+/// numbers them, so that the footnote references the model wrote itself are left as they are.
+/// They are private-use characters, like the markers of ChatGPT.
+const REFERENCE_START: char = '\u{E000}';
+const REFERENCE_END: char = '\u{E001}';
+
+/// A citation marker of the text of a ChatGPT message, as listed in the metadata of the message.
+struct Reference {
+    /// Position of the marker in the text, used to replace the markers in order.
+    start: u64,
+    /// The marker as it appears in the text, e.g. `【12†source】`.
+    matched_text: String,
+    /// Title and URL of each cited page.
+    sources: Vec<(Option<String>, String)>,
+    /// Text replacing a marker that cites no page, if any.
+    alt: Option<String>,
+}
+
+/// Replaces the citation markers of the text of a ChatGPT message with Markdown footnote
+/// references.
+///
+/// Answers written with web browsing cite their sources with markers inserted in the text, such
+/// as `【12†source】` in older exports or `citeturn0search1` wrapped in private-use characters
+/// in recent ones. The pages they stand for are listed in the metadata of the message, under
+/// `citations` (older exports) or `content_references`. Each marker citing pages is replaced
+/// with a footnote reference per page, numbered from 1 in the order the pages are first cited.
+/// The references are left for `number_citations` to write as `[^1][^2]`. Markers citing no page
+/// are replaced with their alternative text, if any, or removed, as are the markers the metadata
+/// does not list, except in code spans and blocks.
+///
+/// # Arguments
+///
+/// * `text` - The text of the message.
+///
+/// * `metadata` - The `MessageMetadata` of the message.
+///
+/// # Returns
+///
+/// The text with footnote references, to be written by `number_citations`, and the `Citation`
+/// each of them refers to.
+pub fn cite_sources(text: &str, metadata: &MessageMetadata) -> (String, Vec<Citation>) {
+    let mut references = content_references(metadata);
+    references.extend(legacy_citations(text, metadata));
+    references.sort_by_key(|reference| reference.start);
+
+    let mut cited = String::with_capacity(text.len());
+    let mut citations: Vec<Citation> = Vec::new();
+    let mut rest = 0;
+    for reference in references {
+        if reference.matched_text.trim().is_empty() {
+            continue;
+        }
+        let Some(position) = text[rest..].find(&reference.matched_text) else {
+            continue;
+        };
+        cited.push_str(&text[rest..rest + position]);
+        rest += position + reference.matched_text.len();
+        if reference.sources.is_empty() {
+            cited.push_str(reference.alt.as_deref().unwrap_or_default());
+            continue;
+        }
+        let mut numbers = Vec::new();
+        for (title, url) in reference.sources {
+            let number = match citations.iter().find(|citation| citation.url == url) {
+                Some(citation) => citation.number,
+                None => {
+                    let number = citations.len() + 1;
+                    citations.push(Citation { number, title, url });
+                    number
+                }
+            };
+            if !numbers.contains(&number) {
+                numbers.push(number);
+                cited.push_str(&format!("{}{}{}", REFERENCE_START, number, REFERENCE_END));
+            }
+        }
+    }
+    cited.push_str(&text[rest..]);
+
+    static MARKERS: OnceLock<Regex> = OnceLock::new();
+    let markers = MARKERS.get_or_init(|| {
+        Regex::new(r"【\d+(?::\d+)?(?:†[^】]*)?】|\x{E200}[^\x{E201}]*\x{E201}").unwrap()
+    });
+    let code = code_spans(&cited);
+    let cited = markers.replace_all(&cited, |captures: &Captures| {
+        let marker = captures.get(0).unwrap();
+        if code.iter().any(|span| span.contains(&marker.start())) {
+            marker.as_str().to_string()
+        } else {
+            String::new()
+        }
+    });
+    (cited.into_owned(), citations)
+}
+
+/// Returns the byte ranges of the code spans and code blocks of a Markdown text.
+fn code_spans(text: &str) -> Vec<Range<usize>> {
+    Parser::new(text)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Code(_) | Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Numbers the citations of the items of a conversation from 1, in the order of the items, so
+/// that their footnotes do not collide once rendered in the same document.
+///
+/// The citations of each item are expected to be numbered from 1, as returned by
+/// `cite_sources`. The footnote references inserted by `cite_sources` are written as `[^1]`
+/// with their new number, while the footnote references of the text itself are left as they are.
+pub fn number_citations(items: &mut [ConversationItem]) {
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| Regex::new(r"\x{E000}(\d+)\x{E001}").unwrap());
+    let mut offset = 0;
+    for item in items {
+        let count = item.citations.len();
+        if count == 0 {
+            continue;
+        }
+        item.text = reference
+            .replace_all(&item.text, |captures: &Captures| {
+                let number = captures[1].parse::<usize>().unwrap_or_default();
+                format!("[^{}]", number + offset)
+            })
+            .into_owned();
+        for citation in &mut item.citations {
+            citation.number += offset;
+        }
+        offset += count;
+    }
+}
+
+/// Lists the `content_references` of a message, the citations of recent exports.
+fn content_references(metadata: &MessageMetadata) -> Vec<Reference> {
+    let Some(references) = metadata
+        .additional_metadata
+        .get("content_references")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    references
+        .iter()
+        .filter_map(|reference| {
+            let matched_text = reference.get("matched_text")?.as_str()?.to_string();
+            // Grouped citations list their pages as `items`, single ones may hold the page.
+            let mut sources: Vec<_> = reference
+                .get("items")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(source)
+                .collect();
+            if sources.is_empty() {
+                sources.extend(source(reference));
+            }
+            Some(Reference {
+                start: reference
+                    .get("start_idx")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0),
+                matched_text,
+                sources,
+                alt: reference
+                    .get("alt")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Lists the `citations` of a message, the citations of older exports, which locate their
+/// marker by its start and end positions in characters.
+fn legacy_citations(text: &str, metadata: &MessageMetadata) -> Vec<Reference> {
+    let Some(citations) = metadata
+        .additional_metadata
+        .get("citations")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    citations
+        .iter()
+        .filter_map(|citation| {
+            let start = citation.get("start_ix")?.as_u64()?;
+            let end = citation.get("end_ix")?.as_u64()?;
+            let matched_text: String = text
+                .chars()
+                .skip(start as usize)
+                .take(end.saturating_sub(start) as usize)
+                .collect();
+            // Positions that do not delimit a marker would replace the text around it.
+            if !matched_text.starts_with('【') {
+                return None;
+            }
+            Some(Reference {
+                start,
+                matched_text,
+                sources: citation
+                    .get("metadata")
+                    .and_then(source)
+                    .into_iter()
+                    .collect(),
+                alt: None,
+            })
+        })
+        .collect()
+}
+
+/// Returns the title and URL of a cited page, or `None` if the page has no URL.
+fn source(page: &Value) -> Option<(Option<String>, String)> {
+    let url = page.get("url")?.as_str()?.to_string();
+    let title = page
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.trim().is_empty())
+        .map(str::to_string);
+    Some((title, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(value: Value) -> MessageMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Writes the footnote references inserted by `cite_sources` as `number_citations` does.
+    fn numbered(text: &str, citations: &[Citation]) -> String {
+        let mut item = ConversationItem::new(text.to_string(), "assistant".to_string(), 0.0);
+        item.citations = citations.to_vec();
+        number_citations(std::slice::from_mut(&mut item));
+        item.text
+    }
+
+    fn reference(number: usize) -> String {
+        format!("{}{}{}", REFERENCE_START, number, REFERENCE_END)
+    }
+
+    #[test]
+    fn test_cite_sources_legacy() {
+        let text = "Rust 1.0 was released in 2015【7†source】. It is fast【9†source】【7†source】.";
+        let metadata = metadata(json!({
+            "citations": [
+                {"start_ix": 29, "end_ix": 39, "metadata": {"title": "Rust Blog", "url": "https://blog.rust-lang.org/"}},
+                {"start_ix": 51, "end_ix": 61, "metadata": {"title": "", "url": "https://example.com/"}},
+                {"start_ix": 61, "end_ix": 71, "metadata": {"title": "Rust Blog", "url": "https://blog.rust-lang.org/"}}
+            ]
+        }));
+
+        let (text, citations) = cite_sources(text, &metadata);
+
+        assert_eq!(
+            numbered(&text, &citations),
+            "Rust 1.0 was released in 2015[^1]. It is fast[^2][^1]."
+        );
+        assert_eq!(
+            citations,
+            vec![
+                Citation {
+                    number: 1,
+                    title: Some("Rust Blog".to_string()),
+                    url: "https://blog.rust-lang.org/".to_string()
+                },
+                Citation {
+                    number: 2,
+                    title: None,
+                    url: "https://example.com/".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cite_sources_content_references() {
+        let marker = "\u{e200}cite\u{e202}turn0search0\u{e202}turn0search1\u{e201}";
+        let text = format!(
+            "Paris is the capital of France {}.\u{e200}cite\u{e202}turn0news9\u{e201} See also \u{e200}entity\u{e202}[\"city\"]\u{e201}. 【3†source】",
+            marker
+        );
+        let metadata = metadata(json!({
+            "content_references": [
+                {
+                    "matched_text": marker,
+                    "start_idx": 31,
+                    "type": "grouped_webpages",
+                    "items": [
+                        {"title": "Paris - Wikipedia", "url": "https://en.wikipedia.org/wiki/Paris"},
+                        {"title": "France", "url": "https://www.britannica.com/place/France"}
+                    ]
+                },
+                {"matched_text": "\u{e200}entity\u{e202}[\"city\"]\u{e201}", "start_idx": 90, "type": "entity", "alt": "Paris"},
+                {"matched_text": " ", "start_idx": 120, "type": "sources_footnote", "sources": []}
+            ]
+        }));
+
+        let (text, citations) = cite_sources(&text, &metadata);
+
+        assert_eq!(
+            numbered(&text, &citations),
+            "Paris is the capital of France [^1][^2]. See also Paris. "
+        );
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[1].title.as_deref(), Some("France"));
+    }
+
+    #[test]
+    fn test_cite_sources_keeps_code_and_footnotes() {
+        let text = "Use `【3†source】` or\n\n```\nprint(\"【4†source】\")\n```\n\nSee [^1] and 【5†source】.";
+
+        let (text, citations) = cite_sources(text, &metadata(json!({})));
+
+        assert_eq!(
+            numbered(&text, &citations),
+            "Use `【3†source】` or\n\n```\nprint(\"【4†source】\")\n```\n\nSee [^1] and ."
+        );
+        assert!(citations.is_empty());
+    }
+
+    #[test]
+    fn test_number_citations() {
+        let citation = |number: usize, url: &str| Citation {
+            number,
+            title: None,
+            url: url.to_string(),
+        };
+        let mut first =
+            ConversationItem::new(format!("A{}.", reference(1)), "assistant".to_string(), 0.0);
+        first.citations = vec![citation(1, "https://a.example/")];
+        let question = ConversationItem::new("Footnote[^1]?".to_string(), "user".to_string(), 0.0);
+        let mut second = ConversationItem::new(
+            format!("B{}{}, C[^1][^9].", reference(2), reference(1)),
+            "assistant".to_string(),
+            0.0,
+        );
+        second.citations = vec![
+            citation(1, "https://b.example/"),
+            citation(2, "https://c.example/"),
+        ];
+        let mut items = vec![first, question, second];
+
+        number_citations(&mut items);
+
+        assert_eq!(items[0].text, "A[^1].");
+        assert_eq!(items[1].text, "Footnote[^1]?");
+        assert_eq!(items[2].text, "B[^3][^2], C[^1][^9].");
+        let numbers: Vec<usize> = items[2].citations.iter().map(|c| c.number).collect();
+        assert_eq!(numbers, vec![2, 3]);
+    }
+}
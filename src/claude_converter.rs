@@ -0,0 +1,301 @@
+use crate::{
+    importer::{ImportOptions, Importer},
+    model::{ClaudeConversation, ClaudeMessage, Conversation, ConversationItem, ItemKind},
+    utils::{date_from_epoch_time, epoch_time_from_rfc3339},
+};
+use log::debug;
+use serde_json::Value;
+
+/// `Importer` for the `conversations.json` file of a Claude.ai data export.
+///
+/// Claude conversations are recognized by their `chat_messages` field. Their messages are
+/// already stored in display order, so every branch option is ignored.
+pub struct ClaudeImporter;
+
+impl Importer for ClaudeImporter {
+    fn name(&self) -> &'static str {
+        "claude"
+    }
+
+    fn detect(&self, conversation: &Value) -> bool {
+        conversation
+            .get("chat_messages")
+            .is_some_and(Value::is_array)
+    }
+
+    fn import(
+        &self,
+        conversation: Value,
+        options: &ImportOptions,
+    ) -> Result<Vec<Conversation>, serde_json::Error> {
+        let conversation: ClaudeConversation = serde_json::from_value(conversation)?;
+        Ok(vec![create_conversation_from_claude(conversation, options)])
+    }
+}
+
+/// Creates a `Conversation` object from a `ClaudeConversation` of a Claude.ai export.
+///
+/// # Arguments
+///
+/// * `claude_conversation` - A `ClaudeConversation` holding the messages of the conversation in
+///   display order, along with its name and timestamps.
+///
+/// * `options` - The `ImportOptions` of the export, telling whether the reasoning of the answers
+///   is kept.
+///
+/// # Returns
+///
+/// A `Conversation` object that includes:
+/// - A list of `ConversationItem` objects, one for each message with content, preceded for
+///   answers written with extended thinking by an item of kind `ItemKind::Reasoning` if
+///   `options.reasoning` is set.
+/// - The name of the conversation, or "Untitled" when the conversation was never named.
+/// - The most recent update date, derived from the `updated_at` field of the conversation.
+pub(crate) fn create_conversation_from_claude(
+    claude_conversation: ClaudeConversation,
+    options: &ImportOptions,
+) -> Conversation {
+    let title = if claude_conversation.name.trim().is_empty() {
+        "Untitled".to_string()
+    } else {
+        claude_conversation.name
+    };
+    let message_count = claude_conversation.chat_messages.len();
+    let conversation_items: Vec<ConversationItem> = claude_conversation
+        .chat_messages
+        .into_iter()
+        .flat_map(|message| process_claude_message(message, &title, options))
+        .collect();
+    debug!(
+        "Conversation '{}': {} items from {} messages",
+        title,
+        conversation_items.len(),
+        message_count
+    );
+
+    let update_time = epoch_time_from_rfc3339(&claude_conversation.updated_at).unwrap_or(0.0);
+    let mut conversation =
+        Conversation::new(title, conversation_items, date_from_epoch_time(update_time));
+    conversation.id = Some(claude_conversation.uuid);
+    conversation.create_time =
+        epoch_time_from_rfc3339(&claude_conversation.created_at).unwrap_or(0.0);
+    conversation.update_time = update_time;
+    conversation
+}
+
+/// Processes a `ClaudeMessage` and extracts its `ConversationItem`s.
+///
+/// The text of the message is taken from its "text" content blocks, falling back to the `text`
+/// field for older exports without blocks. The extracted content of each attachment is appended
+/// as a fenced block, and uploaded files are listed by name, so that the answers referring to
+/// them remain understandable. The "thinking" blocks of answers written with extended thinking
+/// are joined into an item of kind `ItemKind::Reasoning`, which has no id of its own, if
+/// `options.reasoning` is set.
+///
+/// # Returns
+///
+/// A `ConversationItem` authored by "user" for "human" messages and by "assistant" for
+/// assistant messages, identified by the `uuid` of the message and preceded by the reasoning of
+/// the answer if any. Nothing is returned for other senders and for messages without any
+/// content.
+fn process_claude_message(
+    message: ClaudeMessage,
+    title: &str,
+    options: &ImportOptions,
+) -> Vec<ConversationItem> {
+    let role = match message.sender.as_str() {
+        "human" => "user",
+        "assistant" => "assistant",
+        sender => {
+            debug!(
+                "Conversation '{}': skipped {} message {}",
+                title, sender, message.uuid
+            );
+            return Vec::new();
+        }
+    };
+    let time = epoch_time_from_rfc3339(&message.created_at).unwrap_or(0.0);
+
+    let mut items = Vec::new();
+    let thinking: Vec<&str> = message
+        .content
+        .iter()
+        .filter(|block| block.content_type == "thinking")
+        .filter_map(|block| block.thinking.as_deref())
+        .map(str::trim)
+        .filter(|thinking| !thinking.is_empty())
+        .collect();
+    if !thinking.is_empty() && !options.reasoning {
+        debug!(
+            "Conversation '{}': skipped the reasoning of message {}",
+            title, message.uuid
+        );
+    } else if !thinking.is_empty() {
+        let mut reasoning =
+            ConversationItem::new(thinking.join("\n\n"), "assistant".to_string(), time);
+        reasoning.kind = ItemKind::Reasoning { recap: None };
+        items.push(reasoning);
+    }
+
+    let blocks: Vec<&str> = message
+        .content
+        .iter()
+        .filter(|block| block.content_type == "text")
+        .filter_map(|block| block.text.as_deref())
+        .collect();
+    let mut text = if blocks.is_empty() {
+        message.text
+    } else {
+        blocks.join("\n")
+    };
+
+    for attachment in &message.attachments {
+        text.push_str(&format!("\n\n*Attachment: {}*", attachment.file_name));
+        if !attachment.extracted_content.trim().is_empty() {
+            text.push_str(&format!(
+                "\n\n```\n{}\n```",
+                attachment.extracted_content.trim_end()
+            ));
+        }
+    }
+    for file in &message.files {
+        text.push_str(&format!("\n\n*File: {}*", file.file_name));
+    }
+
+    if text.trim().is_empty() {
+        debug!(
+            "Conversation '{}': skipped empty {} message {}",
+            title, message.sender, message.uuid
+        );
+        return items;
+    }
+    let mut item = ConversationItem::new(text.trim_start().to_string(), role.to_string(), time);
+    item.id = Some(message.uuid);
+    items.push(item);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn raw_conversation() -> Value {
+        json!({
+            "uuid": "c1",
+            "name": "Claude chat",
+            "created_at": "2024-03-01T10:00:00.000000Z",
+            "updated_at": "2024-03-02T10:00:00.000000Z",
+            "account": {"uuid": "a1"},
+            "chat_messages": [
+                {
+                    "uuid": "m1",
+                    "text": "Summarize this",
+                    "content": [{"type": "text", "text": "Summarize this"}],
+                    "sender": "human",
+                    "created_at": "2024-03-01T10:00:00.000000Z",
+                    "updated_at": "2024-03-01T10:00:00.000000Z",
+                    "attachments": [
+                        {"file_name": "notes.txt", "file_size": 11, "file_type": "txt", "extracted_content": "Hello notes"}
+                    ],
+                    "files": [{"file_name": "diagram.png"}]
+                },
+                {
+                    "uuid": "m2",
+                    "text": "",
+                    "content": [
+                        {"type": "thinking", "thinking": "The notes only say hello.", "summaries": []},
+                        {"type": "text", "text": "Here is a summary."},
+                        {"type": "tool_use", "name": "search", "input": {}}
+                    ],
+                    "sender": "assistant",
+                    "created_at": "2024-03-01T10:00:05.000000Z",
+                    "updated_at": "2024-03-01T10:00:05.000000Z",
+                    "attachments": [],
+                    "files": []
+                },
+                {
+                    "uuid": "m3",
+                    "text": "   ",
+                    "sender": "human",
+                    "created_at": "2024-03-01T10:01:00.000000Z",
+                    "updated_at": "2024-03-01T10:01:00.000000Z"
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_claude_importer() {
+        let raw = raw_conversation();
+        assert!(ClaudeImporter.detect(&raw));
+
+        let conversations = ClaudeImporter
+            .import(raw, &ImportOptions::default())
+            .unwrap();
+
+        assert_eq!(conversations.len(), 1);
+        let conversation = &conversations[0];
+        assert_eq!(conversation.title, "Claude chat");
+        assert_eq!(conversation.date, "2024-03-02");
+        assert_eq!(conversation.id.as_deref(), Some("c1"));
+        assert_eq!(conversation.items.len(), 2);
+        assert_eq!(conversation.items[0].author, "user");
+        assert_eq!(
+            conversation.items[0].text,
+            "Summarize this\n\n*Attachment: notes.txt*\n\n```\nHello notes\n```\n\n*File: diagram.png*"
+        );
+        assert_eq!(conversation.items[1].author, "assistant");
+        assert_eq!(conversation.items[1].text, "Here is a summary.");
+        assert_eq!(conversation.items[1].id.as_deref(), Some("m2"));
+    }
+
+    #[test]
+    fn test_claude_importer_reasoning() {
+        let options = ImportOptions {
+            reasoning: true,
+            ..ImportOptions::default()
+        };
+
+        let conversations = ClaudeImporter.import(raw_conversation(), &options).unwrap();
+
+        let items = &conversations[0].items;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1].kind, ItemKind::Reasoning { recap: None });
+        assert_eq!(items[1].text, "The notes only say hello.");
+        assert_eq!(items[1].author, "assistant");
+        assert_eq!(items[1].id, None);
+        assert_eq!(items[2].text, "Here is a summary.");
+    }
+
+    #[test]
+    fn test_create_conversation_from_claude_untitled() {
+        let mut raw = raw_conversation();
+        raw["name"] = json!("");
+        let conversation: ClaudeConversation = serde_json::from_value(raw).unwrap();
+
+        assert_eq!(
+            create_conversation_from_claude(conversation, &ImportOptions::default()).title,
+            "Untitled"
+        );
+    }
+
+    #[test]
+    fn test_claude_importer_null_fields() {
+        let mut raw = raw_conversation();
+        raw["name"] = Value::Null;
+        raw["chat_messages"][0]["attachments"][0]["extracted_content"] = Value::Null;
+        raw["chat_messages"][0]["files"] = Value::Null;
+        raw["chat_messages"][1]["text"] = Value::Null;
+
+        let conversations = ClaudeImporter
+            .import(raw, &ImportOptions::default())
+            .unwrap();
+
+        assert_eq!(conversations[0].title, "Untitled");
+        assert_eq!(
+            conversations[0].items[0].text,
+            "Summarize this\n\n*Attachment: notes.txt*"
+        );
+    }
+}
@@ -1,142 +1,523 @@
-use crate::{model::Conversation, utils::normalized_filename_string};
+use crate::{
+    error::{Error, Errors},
+    exporter::{Exporter, IndexEntry},
+    layout::{path_to_root, Layout},
+    manifest::{content_hash, Changes, Manifest, ManifestEntry},
+    model::Conversation,
+    utils::normalized_filename_string,
+};
+use log::{debug, info, warn};
 use rayon::prelude::*;
-use std::{fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
 
-/// Converts a `Conversation` object into a Markdown formatted string.
+/// Names of the files of an export, claimed by each of its conversations before any file is
+/// written.
 ///
-/// This function takes a `Conversation` struct, iterates over its items, and constructs
-/// a Markdown representation of the conversation. Each item in the conversation is prefixed
-/// with a section title based on the author of the message, distinguishing between user
-/// input and system responses.
-///
-/// # Arguments
-///
-/// * `conversation` - A `Conversation` object that contains a title and a collection of
-///   conversation items, each with an author and text content.
-///
-/// # Returns
-///
-/// A `String` containing the entire conversation formatted as Markdown. The output begins
-/// with an H1 title derived from the conversation's title, followed by each item formatted
-/// as an H2 section. Items authored by "user" are labeled "Question" and other items are
-/// labeled "Answer".
-///
-/// # Example
-///
-/// ```
-/// let conversation = Conversation {
-///     title: String::from("Sample Conversation"),
-///     items: vec![
-///         ConversationItem { author: String::from("user"), text: String::from("What is the weather today?") },
-///         ConversationItem { author: String::from("assistant"), text: String::from("The weather is sunny today.") },
-///     ],
-/// };
-/// let markdown = conversation_to_md(conversation);
-/// println!("{}", markdown);
-/// // Output:
-/// // # Sample Conversation
-/// //
-/// // ## Question
-/// // What is the weather today?
-/// //
-/// // ## Answer
-/// // The weather is sunny today.
-/// //
-fn conversation_to_md(conversation: Conversation) -> String {
-    let mut content = format!("# {}\n\n", conversation.title);
-
-    for item in conversation.items {
-        let section_title = if item.author == "user" {
-            "Question"
-        } else {
-            "Answer"
-        };
-        content.push_str(&format!("## {}\n{}\n\n", section_title, item.text));
+/// Each file is named using a combination of the conversation's date and a normalized version of
+/// the title, in the subfolder of the output folder given by the `Layout`. Conversations that
+/// would share the same file names, such as two "New chat" on the same day, are told apart: the
+/// earliest created keeps the name, and the later ones receive a suffix after the title (see
+/// `suffixes`). The names depend neither on the order in which the conversations are processed
+/// nor on the conversations created later, so that exporting a newer data export does not
+/// rename the files written by previous runs.
+#[derive(Debug, Default)]
+pub struct FileNames {
+    layout: Layout,
+    /// Conversations claiming each file name.
+    claims: Mutex<HashMap<String, Vec<Claimant>>>,
+}
+
+impl FileNames {
+    /// Creates the names of the files of an export organized by `layout`, not claimed by any
+    /// conversation yet.
+    pub fn new(layout: Layout) -> FileNames {
+        FileNames {
+            layout,
+            claims: Mutex::default(),
+        }
+    }
+
+    /// Creates the names of the files of conversations held in memory, each of them claiming
+    /// its name.
+    pub fn of(conversations: &[Conversation], layout: Layout) -> FileNames {
+        let names = FileNames::new(layout);
+        for conversation in conversations {
+            names.claim(conversation);
+        }
+        names
+    }
+
+    /// Claims the name of the files of `conversation`. Every conversation of the export, or
+    /// branch of a conversation, must claim its name before `write` is given the names.
+    pub fn claim(&self, conversation: &Conversation) {
+        let name = self.name(conversation);
+        let mut claims = self.claims.lock().unwrap();
+        claim(claims.entry(name).or_default(), conversation);
+    }
+
+    /// Returns the name of the files of a conversation, before disambiguation and without the
+    /// branch suffix and extension.
+    fn name(&self, conversation: &Conversation) -> String {
+        format!(
+            "{}{}-{}",
+            self.layout.folder(conversation),
+            conversation.date.replace(['/', '\\', ':'], "-"),
+            normalized_filename_string(&conversation.title, 40)
+        )
     }
-    content
 }
 
-/// Writes a collection of `Conversation` objects to markdown files in a specified output folder.
+/// Writes a collection of `Conversation` objects to files in a specified output folder, once per
+/// exporter.
+///
+/// This function processes each `Conversation` object in parallel as it is produced, renders it
+/// with each of the given `Exporter`s, and saves the result as a file in the specified output
+/// directory. Each file is named by `names`, followed by a `-branch-N-of-M` suffix for
+/// conversations that are one of several branches, so that all branches share the same prefix,
+/// and by the extension of the exporter. Once every conversation is written, each exporter may
+/// add an index file. If the output directory does not exist, it attempts to create it.
+///
+/// Files are written in the subfolder of the output folder given by the layout of `names`, and
+/// the images they link to are linked relative to that subfolder.
 ///
-/// This function processes each `Conversation` object in the provided vector, converts it to a
-/// Markdown string using the `conversation_to_md` function, and saves it as a file in the specified
-/// output directory. Each file is named using a combination of the conversation's date and a
-/// normalized version of the title. If the output directory does not exist, it attempts to create it.
+/// Files whose content did not change since the run recorded in `manifest` are left untouched,
+/// so that re-exporting an updated data export only rewrites new and updated conversations.
 ///
 /// # Arguments
 ///
-/// * `conversations` - A `Vec<Conversation>` containing the conversations to be written to files. Each
-///   `Conversation` includes a title and date that contribute to the naming of the output files.
+/// * `conversations` - The conversations to be written to files, such as a `Vec<Conversation>` or a
+///   parallel iterator streaming them. Each `Conversation` includes a title and date that
+///   contribute to the naming of the output files. Only the title, date and name of the written
+///   files are kept once a conversation is written, for the indexes.
 ///
-/// * `output_folder` - A path that specifies the directory where the markdown files will be saved. The
+/// * `output_folder` - A path that specifies the directory where the files will be saved. The
 ///   path is generic and can be converted into a `Path`.
 ///
+/// * `exporters` - The `Exporter`s rendering the files, one file per conversation and exporter.
+///
+/// * `names` - The `FileNames` claimed by every conversation beforehand, such as
+///   `FileNames::of(&conversations, layout)` for conversations held in memory. A conversation
+///   that did not claim its name is written with a suffix, so that it does not take the name
+///   of another conversation.
+///
+/// * `manifest` - The `Manifest` of the previous runs in the output folder, updated with every
+///   file produced by this run. The files of the conversations that could not be written are
+///   kept, so that `Manifest::prune` does not remove them. It is up to the caller to load and
+///   save it.
+///
+/// # Returns
+///
+/// The `Changes` made to the output folder, with the created, updated and disambiguated files
+/// sorted by path, and the errors that occurred, in the order they occurred.
+///
 /// # Errors
 ///
-/// Errors during directory creation or file writing are logged to the standard error output.
-/// This includes failures such as inability to create the directory or to write a file, along
-/// with associated error messages.
-pub fn write<P>(conversations: Vec<Conversation>, output_folder: P)
+/// Errors during directory creation or file writing are logged as they occur and returned as
+/// `Error::Write`, and the files concerned are left out of the `Changes`, the manifest and the
+/// indexes. The other files are still written. If the output folder itself cannot be created,
+/// the conversations are still consumed, but none is written.
+pub fn write<I, P>(
+    conversations: I,
+    output_folder: P,
+    exporters: &[Box<dyn Exporter>],
+    mut names: FileNames,
+    manifest: &mut Manifest,
+) -> (Changes, Vec<Error>)
 where
+    I: IntoParallelIterator<Item = Conversation>,
     P: AsRef<Path>,
 {
+    let mut changes = Changes::default();
+    let errors = Errors::default();
     let folder = output_folder.as_ref();
     if let Err(err) = fs::create_dir_all(folder) {
-        eprintln!("Failed to create directory '{}': {}", folder.display(), err);
-        return;
+        errors.push(Error::write(folder, err));
+        // Consumes the conversations anyway, so that a producer streaming them does not fail
+        // on a closed channel and hide this error.
+        let ids: Vec<String> = conversations
+            .into_par_iter()
+            .filter_map(|conversation| conversation.id)
+            .collect();
+        for id in &ids {
+            manifest.keep(id);
+        }
+        return (changes, errors.into_inner());
     }
-    conversations.into_par_iter().for_each(|conversation| {
-        let filename = format!(
-            "{}-{}.md",
-            conversation.date,
-            normalized_filename_string(&conversation.title, 40)
-        );
-        let path = folder.join(filename);
-        let content = conversation_to_md(conversation);
+    let mut entries: Vec<Vec<IndexEntry>> = exporters.iter().map(|_| Vec::new()).collect();
+    let previous: &Manifest = manifest;
+    let claims = std::mem::take(names.claims.get_mut().unwrap());
+    let suffixes: HashMap<&str, Vec<Option<String>>> = claims
+        .iter()
+        .filter(|(_, claimants)| claimants.len() > 1)
+        .map(|(name, claimants)| {
+            info!(
+                "{} conversations would be written to '{}', telling them apart with a suffix",
+                claimants.len(),
+                name
+            );
+            (name.as_str(), suffixes(claimants))
+        })
+        .collect();
+    // Branches of the claimants already given their name, so that a conversation found twice in
+    // the export is given the name of its second claim.
+    let named: Mutex<HashSet<(String, usize, Option<usize>)>> = Mutex::default();
+    // Ids of the conversations with a file that could not be written.
+    let failed: Mutex<HashSet<String>> = Mutex::default();
+    let written: Vec<WrittenFile> = conversations
+        .into_par_iter()
+        .flat_map_iter(|mut conversation| {
+            let subfolder = names.layout.folder(&conversation);
+            let to_root = path_to_root(&subfolder);
+            for item in &mut conversation.items {
+                for path in item
+                    .images
+                    .iter_mut()
+                    .filter_map(|image| image.path.as_mut())
+                {
+                    path.insert_str(0, &to_root);
+                }
+            }
+            let name = names.name(&conversation);
+            let branch = conversation.branch.map(|branch| branch.index);
+            let claimant = {
+                let mut named = named.lock().unwrap();
+                claims.get(&name).and_then(|claimants| {
+                    (0..claimants.len()).find(|&index| {
+                        claimants[index].claims(&conversation)
+                            && named.insert((name.clone(), index, branch))
+                    })
+                })
+            };
+            let suffix = match claimant {
+                Some(claimant) => suffixes
+                    .get(name.as_str())
+                    .and_then(|suffixes| suffixes[claimant].clone()),
+                None => {
+                    warn!(
+                        "Conversation '{}' did not claim the name '{}', telling it apart with a \
+                         suffix",
+                        conversation.title, name
+                    );
+                    Some(disambiguator(&conversation))
+                }
+            };
+            let stem = match &suffix {
+                Some(suffix) => format!("{}-{}", name, suffix),
+                None => name,
+            };
+            let branch_suffix = conversation
+                .branch
+                .map(|branch| format!("-branch-{}-of-{}", branch.index, branch.count))
+                .unwrap_or_default();
+            exporters
+                .iter()
+                .enumerate()
+                .filter_map(|(index, exporter)| {
+                    let filename = format!("{}{}.{}", stem, branch_suffix, exporter.extension());
+                    let content = exporter.render(&conversation);
+                    let manifest_entry = ManifestEntry {
+                        id: conversation.id.clone(),
+                        update_time: conversation.update_time,
+                        hash: content_hash(&content),
+                    };
+                    let status = write_file(folder, &filename, &content, &manifest_entry, previous)
+                        .map_err(|err| {
+                            if let Some(id) = &conversation.id {
+                                failed.lock().unwrap().insert(id.clone());
+                            }
+                            errors.push(err)
+                        })
+                        .ok()?;
+                    Some(WrittenFile {
+                        exporter: index,
+                        entry: IndexEntry {
+                            title: conversation.title.clone(),
+                            date: conversation.date.clone(),
+                            update_time: conversation.update_time,
+                            filename,
+                        },
+                        manifest_entry,
+                        status,
+                        disambiguated: suffix.is_some(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
 
-        if let Err(err) = fs::write(&path, content) {
-            eprintln!("Failed to write file '{}': {}", path.display(), err);
+    for file in written {
+        if file.disambiguated {
+            changes.disambiguated.push(file.entry.filename.clone());
         }
+        changes.add(file.status, &file.entry.filename);
+        manifest.record(file.entry.filename.clone(), file.manifest_entry);
+        entries[file.exporter].push(file.entry);
+    }
+    for id in failed.into_inner().unwrap() {
+        manifest.keep(&id);
+    }
+
+    for (exporter, entries) in exporters.iter().zip(entries) {
+        let Some(index) = exporter.render_index(&entries) else {
+            continue;
+        };
+        let filename = format!("index.{}", exporter.extension());
+        let manifest_entry = ManifestEntry {
+            id: None,
+            update_time: 0.0,
+            hash: content_hash(&index),
+        };
+        match write_file(folder, &filename, &index, &manifest_entry, manifest) {
+            Ok(status) => {
+                changes.add(status, &filename);
+                manifest.record(filename, manifest_entry);
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    changes.created.sort();
+    changes.updated.sort();
+    changes.disambiguated.sort();
+    (changes, errors.into_inner())
+}
+
+/// Returns the suffix telling a conversation apart from the other conversations of the export
+/// that would be written to the same files: the first 8 alphanumeric characters of its id, or its
+/// creation time for exports without ids.
+fn disambiguator(conversation: &Conversation) -> String {
+    let id: String = conversation
+        .id
+        .as_deref()
+        .map(alphanumeric)
+        .unwrap_or_default()
+        .chars()
+        .take(8)
+        .collect();
+    if id.is_empty() {
+        format!("{}", conversation.create_time as i64)
+    } else {
+        id
+    }
+}
+
+/// Returns the ASCII alphanumeric characters of an id.
+fn alphanumeric(id: &str) -> String {
+    id.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+/// A conversation claiming a file name.
+#[derive(Debug)]
+struct Claimant {
+    disambiguator: String,
+    id: Option<String>,
+    create_time: f64,
+    /// Index of each branch of the conversation claiming the name, `None` for a conversation
+    /// written without branches.
+    branches: HashSet<Option<usize>>,
+}
+
+impl Claimant {
+    /// Tells whether `conversation` is the conversation, or one of the branches, that made
+    /// this claim.
+    fn claims(&self, conversation: &Conversation) -> bool {
+        self.id == conversation.id
+            && self.create_time == conversation.create_time
+            && self
+                .branches
+                .contains(&conversation.branch.map(|branch| branch.index))
+    }
+}
+
+/// Adds `conversation` to the `claimants` of its file name, unless it is another branch of a
+/// conversation that already claimed it.
+fn claim(claimants: &mut Vec<Claimant>, conversation: &Conversation) {
+    let branch = conversation.branch.map(|branch| branch.index);
+    let existing = claimants.iter().position(|claimant| {
+        claimant.id == conversation.id
+            && claimant.create_time == conversation.create_time
+            && !claimant.branches.contains(&branch)
+    });
+    let index = existing.unwrap_or_else(|| {
+        claimants.push(Claimant {
+            disambiguator: disambiguator(conversation),
+            id: conversation.id.clone(),
+            create_time: conversation.create_time,
+            branches: HashSet::new(),
+        });
+        claimants.len() - 1
     });
+    claimants[index].branches.insert(branch);
+}
+
+/// Returns the suffix of the files of each of the `claimants` of a file name, `None` for the
+/// files that keep the name.
+///
+/// The earliest created conversation keeps the name, so that the conversations created after it
+/// do not rename its files. Each of the other conversations is suffixed with its disambiguator.
+/// When several of them share the same disambiguator, such as conversations without id created
+/// in the same second or ids with the same first 8 alphanumeric characters, they are suffixed
+/// with their whole id if they all have a distinct one, or else with their disambiguator
+/// followed by their rank by creation time.
+fn suffixes(claimants: &[Claimant]) -> Vec<Option<String>> {
+    let by_creation = |a: &usize, b: &usize| {
+        claimants[*a]
+            .create_time
+            .total_cmp(&claimants[*b].create_time)
+            .then_with(|| claimants[*a].id.cmp(&claimants[*b].id))
+    };
+    let earliest = (0..claimants.len()).min_by(by_creation);
+    let mut suffixes: Vec<Option<String>> = claimants
+        .iter()
+        .map(|claimant| Some(claimant.disambiguator.clone()))
+        .collect();
+    let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, claimant) in claimants.iter().enumerate() {
+        if Some(index) != earliest {
+            groups
+                .entry(&claimant.disambiguator)
+                .or_default()
+                .push(index);
+        }
+    }
+    for (disambiguator, mut group) in groups {
+        if group.len() < 2 {
+            continue;
+        }
+        let ids: Vec<String> = group
+            .iter()
+            .filter_map(|&index| claimants[index].id.as_deref().map(alphanumeric))
+            .filter(|id| id.len() > disambiguator.len())
+            .collect();
+        if ids.len() == group.len() && ids.iter().collect::<HashSet<_>>().len() == ids.len() {
+            for (&index, id) in group.iter().zip(ids) {
+                suffixes[index] = Some(id);
+            }
+            continue;
+        }
+        group.sort_by(by_creation);
+        for (rank, &index) in group.iter().enumerate() {
+            suffixes[index] = Some(format!("{}-{}", disambiguator, rank + 1));
+        }
+    }
+    if let Some(earliest) = earliest {
+        suffixes[earliest] = None;
+    }
+    suffixes
+}
+
+/// A file written for a conversation.
+struct WrittenFile {
+    /// Index of the `Exporter` that rendered the file.
+    exporter: usize,
+    entry: IndexEntry,
+    manifest_entry: ManifestEntry,
+    status: Status,
+    /// Whether the file was given a suffix because several conversations claimed its name.
+    disambiguated: bool,
+}
+
+/// What `write_file` did with a file.
+enum Status {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+impl Changes {
+    fn add(&mut self, status: Status, filename: &str) {
+        match status {
+            Status::Created => self.created.push(filename.to_string()),
+            Status::Updated => self.updated.push(filename.to_string()),
+            Status::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// Writes `content` to `filename` in `folder`, unless the file still has the content recorded in
+/// `manifest`.
+///
+/// # Returns
+///
+/// The `Status` of the file.
+///
+/// # Errors
+///
+/// Returns an `Error::Write` if the file or its folder could not be written, or if `filename`
+/// leads out of `folder`.
+fn write_file(
+    folder: &Path,
+    filename: &str,
+    content: &[u8],
+    entry: &ManifestEntry,
+    manifest: &Manifest,
+) -> Result<Status, Error> {
+    let path = path_in(folder, filename)?;
+    let exists = path.exists();
+    let previous = manifest.get(filename);
+    if exists && previous.is_some_and(|previous| previous.hash == entry.hash) {
+        debug!("Left '{}' unchanged", path.display());
+        return Ok(Status::Unchanged);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::write(parent, err))?;
+    }
+    fs::write(&path, content).map_err(|err| Error::write(&path, err))?;
+    debug!("Wrote '{}'", path.display());
+    Ok(if exists {
+        Status::Updated
+    } else {
+        Status::Created
+    })
+}
+
+/// Returns the path of the file `filename` of `folder`.
+///
+/// # Errors
+///
+/// Returns an `Error::Write` if `filename` is not a relative path inside `folder`, such as an
+/// absolute path or a path with `..` components.
+fn path_in(folder: &Path, filename: &str) -> Result<PathBuf, Error> {
+    let path = folder.join(filename);
+    let inside = Path::new(filename)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if inside {
+        Ok(path)
+    } else {
+        Err(Error::write(
+            &path,
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the file would be written outside of the output folder",
+            ),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::{Conversation, ConversationItem};
+    use crate::date_format::DateFormat;
+    use crate::exporter::{ExportOptions, OutputFormat};
+    use crate::model::{Branch, Conversation, ConversationItem, Image, ItemKind};
     use std::fs;
     use std::path::PathBuf;
 
-    #[test]
-    fn test_conversation_to_md() {
-        let conversation = Conversation {
-            title: "Test Conversation".to_string(),
-            date: "2023-01-01".to_string(),
-            items: vec![
-                ConversationItem {
-                    text: "Hello!".to_string(),
-                    author: "user".to_string(),
-                    time: 1672531200.0,
-                },
-                ConversationItem {
-                    text: "Hi!".to_string(),
-                    author: "assistant".to_string(),
-                    time: 1672531210.0,
-                },
-            ],
-        };
-
-        let markdown = conversation_to_md(conversation);
-        let expected = r#"# Test Conversation
-
-## Question
-Hello!
-
-## Answer
-Hi!
-
-"#;
-        assert_eq!(markdown, expected);
+    /// Writes conversations held in memory once they claimed their names, as `export` does.
+    fn write_all(
+        conversations: Vec<Conversation>,
+        output_folder: impl AsRef<Path>,
+        exporters: &[Box<dyn Exporter>],
+        layout: Layout,
+        manifest: &mut Manifest,
+    ) -> (Changes, Vec<Error>) {
+        let names = FileNames::of(&conversations, layout);
+        write(conversations, output_folder, exporters, names, manifest)
     }
 
     #[test]
@@ -145,14 +526,33 @@ Hi!
             title: "Test Conversation".to_string(),
             date: "2023-01-01".to_string(),
             items: vec![ConversationItem {
+                id: None,
                 text: "Hello!".to_string(),
                 author: "user".to_string(),
                 time: 1672531200.0,
+                kind: ItemKind::Text,
+                images: Vec::new(),
+                model: None,
+                citations: Vec::new(),
             }],
+            branch: None,
+            id: None,
+            create_time: 0.0,
+            update_time: 0.0,
+            model: None,
+            gizmo_id: None,
+            source: None,
+            archived: false,
         }];
 
         let output_folder = PathBuf::from("./test_output");
-        write(conversations, &output_folder);
+        write_all(
+            conversations,
+            &output_folder,
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            Layout::default(),
+            &mut Manifest::default(),
+        );
 
         let output_path = output_folder.join("2023-01-01-Test_Conversation.md");
         assert!(output_path.exists());
@@ -164,4 +564,481 @@ Hi!
         fs::remove_file(output_path).unwrap();
         fs::remove_dir(output_folder).unwrap();
     }
+
+    #[test]
+    fn test_write_branches() {
+        let conversations: Vec<Conversation> = (1..=2)
+            .map(|index| {
+                let mut conversation = Conversation::new(
+                    "Branches".to_string(),
+                    vec![ConversationItem::new(
+                        format!("Answer {}", index),
+                        "assistant".to_string(),
+                        0.0,
+                    )],
+                    "2023-01-01".to_string(),
+                );
+                conversation.branch = Some(Branch::new(index, 2));
+                conversation
+            })
+            .collect();
+
+        let output_folder = PathBuf::from("./test_output_branches");
+        write_all(
+            conversations,
+            &output_folder,
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            Layout::default(),
+            &mut Manifest::default(),
+        );
+
+        let second = output_folder.join("2023-01-01-Branches-branch-2-of-2.md");
+        let content = fs::read_to_string(&second).unwrap();
+        assert!(content.starts_with("# Branches\n\n*Branch 2 of 2*\n\n"));
+        assert!(content.contains("Answer 2"));
+        assert!(output_folder
+            .join("2023-01-01-Branches-branch-1-of-2.md")
+            .exists());
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_html() {
+        let conversations = vec![Conversation::new(
+            "Test Conversation".to_string(),
+            vec![ConversationItem::new(
+                "Hello!".to_string(),
+                "user".to_string(),
+                0.0,
+            )],
+            "2023-01-01".to_string(),
+        )];
+
+        let output_folder = PathBuf::from("./test_output_html");
+        write_all(
+            conversations,
+            &output_folder,
+            &[OutputFormat::Html.exporter(&ExportOptions::default())],
+            Layout::default(),
+            &mut Manifest::default(),
+        );
+
+        let content =
+            fs::read_to_string(output_folder.join("2023-01-01-Test_Conversation.html")).unwrap();
+        assert!(content.contains("<h1>Test Conversation</h1>"));
+        let index = fs::read_to_string(output_folder.join("index.html")).unwrap();
+        assert!(index.contains("href=\"2023-01-01-Test_Conversation.html\""));
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_several_formats() {
+        let conversations = vec![Conversation::new(
+            "Test Conversation".to_string(),
+            vec![ConversationItem::new(
+                "Hello!".to_string(),
+                "user".to_string(),
+                0.0,
+            )],
+            "2023-01-01".to_string(),
+        )];
+
+        let output_folder = PathBuf::from("./test_output_formats");
+        write_all(
+            conversations,
+            &output_folder,
+            &[
+                OutputFormat::Markdown.exporter(&ExportOptions::default()),
+                OutputFormat::Html.exporter(&ExportOptions::default()),
+            ],
+            Layout::default(),
+            &mut Manifest::default(),
+        );
+
+        assert!(output_folder
+            .join("2023-01-01-Test_Conversation.md")
+            .exists());
+        assert!(output_folder
+            .join("2023-01-01-Test_Conversation.html")
+            .exists());
+        assert!(output_folder.join("index.html").exists());
+        assert!(!output_folder.join("index.md").exists());
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_skips_unchanged_files() {
+        let conversation = |answer: &str| {
+            Conversation::new(
+                "Test Conversation".to_string(),
+                vec![ConversationItem::new(
+                    answer.to_string(),
+                    "assistant".to_string(),
+                    0.0,
+                )],
+                "2023-01-01".to_string(),
+            )
+        };
+        let output_folder = PathBuf::from("./test_output_manifest");
+        let exporters = [OutputFormat::Html.exporter(&ExportOptions::default())];
+        let mut manifest = Manifest::default();
+
+        let (changes, _) = write_all(
+            vec![conversation("Hi!")],
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert_eq!(
+            changes.created,
+            vec!["2023-01-01-Test_Conversation.html", "index.html"]
+        );
+
+        let (changes, _) = write_all(
+            vec![conversation("Hi!")],
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert!(changes.created.is_empty() && changes.updated.is_empty());
+        assert_eq!(changes.unchanged, 2);
+
+        let (changes, _) = write_all(
+            vec![conversation("Hello!")],
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert_eq!(changes.updated, vec!["2023-01-01-Test_Conversation.html"]);
+        assert_eq!(changes.unchanged, 1);
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    fn new_chat(id: Option<&str>, create_time: f64) -> Conversation {
+        let mut conversation = Conversation::new(
+            "New chat".to_string(),
+            vec![ConversationItem::new(
+                format!("Created at {}", create_time),
+                "user".to_string(),
+                create_time,
+            )],
+            "2023-01-01".to_string(),
+        );
+        conversation.id = id.map(str::to_string);
+        conversation.create_time = create_time;
+        conversation
+    }
+
+    #[test]
+    fn test_write_disambiguates_colliding_names() {
+        let output_folder = PathBuf::from("./test_output_collisions");
+        let exporters = [OutputFormat::Markdown.exporter(&ExportOptions::default())];
+        let mut manifest = Manifest::default();
+
+        let (changes, _) = write_all(
+            vec![new_chat(Some("aaaa-1111-2222"), 1.0)],
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert_eq!(changes.created, vec!["2023-01-01-New_chat.md"]);
+        assert!(changes.disambiguated.is_empty());
+
+        // The conversations created later are told apart, without renaming the first one.
+        let conversations = || {
+            vec![
+                new_chat(None, 1672531200.0),
+                new_chat(Some("bbbb-3333-4444"), 2.0),
+                new_chat(Some("aaaa-1111-2222"), 1.0),
+            ]
+        };
+        let expected = vec![
+            "2023-01-01-New_chat-1672531200.md",
+            "2023-01-01-New_chat-bbbb3333.md",
+        ];
+        let (changes, _) = write_all(
+            conversations(),
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert_eq!(changes.disambiguated, expected);
+        assert_eq!(changes.created, expected);
+        assert_eq!(changes.unchanged, 1);
+        let content = fs::read_to_string(output_folder.join("2023-01-01-New_chat.md")).unwrap();
+        assert!(content.contains("Created at 1"));
+        let content =
+            fs::read_to_string(output_folder.join("2023-01-01-New_chat-bbbb3333.md")).unwrap();
+        assert!(content.contains("Created at 2"));
+
+        let (changes, _) = write_all(
+            conversations(),
+            &output_folder,
+            &exporters,
+            Layout::default(),
+            &mut manifest,
+        );
+        assert_eq!(changes.disambiguated, expected);
+        assert_eq!(changes.unchanged, 3);
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_disambiguates_shared_disambiguators() {
+        let conversations = || {
+            vec![
+                new_chat(None, 1672531200.7),
+                new_chat(None, 1672531200.5),
+                new_chat(Some("abcd-efgh-1111"), 1672531300.0),
+                new_chat(Some("abcd-efgh-2222"), 1672531400.0),
+                new_chat(None, 1672531200.2),
+            ]
+        };
+        let output_folder = PathBuf::from("./test_output_shared_disambiguators");
+        let exporters = [OutputFormat::Markdown.exporter(&ExportOptions::default())];
+        let mut manifest = Manifest::default();
+        let expected = vec![
+            "2023-01-01-New_chat-1672531200-1.md",
+            "2023-01-01-New_chat-1672531200-2.md",
+            "2023-01-01-New_chat-abcdefgh1111.md",
+            "2023-01-01-New_chat-abcdefgh2222.md",
+        ];
+
+        for run in 0..2 {
+            let mut conversations = conversations();
+            if run == 1 {
+                conversations.reverse();
+            }
+            let (changes, _) = write_all(
+                conversations,
+                &output_folder,
+                &exporters,
+                Layout::default(),
+                &mut manifest,
+            );
+            assert_eq!(changes.disambiguated, expected);
+        }
+
+        let content = fs::read_to_string(output_folder.join("2023-01-01-New_chat.md")).unwrap();
+        assert!(content.contains("Created at 1672531200.2"));
+        let content =
+            fs::read_to_string(output_folder.join("2023-01-01-New_chat-1672531200-1.md")).unwrap();
+        assert!(content.contains("Created at 1672531200.5"));
+        let content =
+            fs::read_to_string(output_folder.join("2023-01-01-New_chat-abcdefgh2222.md")).unwrap();
+        assert!(content.contains("Created at 1672531400"));
+        let mut files: Vec<_> = fs::read_dir(&output_folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        let mut all = expected.clone();
+        all.push("2023-01-01-New_chat.md");
+        assert_eq!(files, all);
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_suffixes_unclaimed_conversations() {
+        let output_folder = PathBuf::from("./test_output_unclaimed");
+        let claimed = vec![new_chat(Some("aaaa-1111"), 2.0)];
+        let names = FileNames::of(&claimed, Layout::default());
+        let mut conversations = claimed;
+        conversations.push(new_chat(Some("bbbb-2222"), 1.0));
+
+        let (changes, _) = write(
+            conversations,
+            &output_folder,
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            names,
+            &mut Manifest::default(),
+        );
+
+        assert_eq!(
+            changes.created,
+            vec!["2023-01-01-New_chat-bbbb2222.md", "2023-01-01-New_chat.md"]
+        );
+        assert_eq!(
+            changes.disambiguated,
+            vec!["2023-01-01-New_chat-bbbb2222.md"]
+        );
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_layout() {
+        let mut item = ConversationItem::new("Look:".to_string(), "user".to_string(), 0.0);
+        let mut image = Image::new("file-abc".to_string());
+        image.path = Some("assets/file-abc.png".to_string());
+        item.images.push(image);
+        let mut conversation =
+            Conversation::new("Photo".to_string(), vec![item], "2023-02-01".to_string());
+        conversation.update_time = 1675209600.0;
+        let output_folder = PathBuf::from("./test_output_layout");
+        let layout = Layout::new("{year}/{month}", DateFormat::default()).unwrap();
+
+        let (changes, _) = write_all(
+            vec![conversation],
+            &output_folder,
+            &[OutputFormat::Html.exporter(&ExportOptions::default())],
+            layout,
+            &mut Manifest::default(),
+        );
+
+        assert_eq!(
+            changes.created,
+            vec!["2023/02/2023-02-01-Photo.html", "index.html"]
+        );
+        let content =
+            fs::read_to_string(output_folder.join("2023/02/2023-02-01-Photo.html")).unwrap();
+        assert!(content.contains("src=\"../../assets/file-abc.png\""));
+        let index = fs::read_to_string(output_folder.join("index.html")).unwrap();
+        assert!(index.contains("href=\"2023/02/2023-02-01-Photo.html\""));
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_reports_errors() {
+        let output_folder = PathBuf::from("./test_output_errors");
+        fs::create_dir_all(&output_folder).unwrap();
+        // A file standing where the folder of the conversation should be created.
+        fs::write(output_folder.join("2023"), "").unwrap();
+        let mut conversation =
+            Conversation::new("Blocked".to_string(), Vec::new(), "2023-02-01".to_string());
+        conversation.update_time = 1675209600.0;
+        let mut manifest = Manifest::default();
+
+        let (changes, errors) = write_all(
+            vec![conversation],
+            &output_folder,
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            Layout::new("{year}", DateFormat::default()).unwrap(),
+            &mut manifest,
+        );
+
+        assert_eq!(changes, Changes::default());
+        assert!(manifest.get("2023/2023-02-01-Blocked.md").is_none());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], Error::Write { .. }));
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_consumes_conversations_when_folder_fails() {
+        let blocker = PathBuf::from("./test_output_blocked");
+        fs::write(&blocker, "").unwrap();
+        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+        let producer = std::thread::spawn(move || {
+            (0..3).all(|index| {
+                let conversation = Conversation::new(
+                    format!("Conversation {}", index),
+                    Vec::new(),
+                    "2023-01-01".to_string(),
+                );
+                sender.send(conversation).is_ok()
+            })
+        });
+
+        let (changes, errors) = write(
+            receiver.into_iter().par_bridge(),
+            blocker.join("output"),
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            FileNames::default(),
+            &mut Manifest::default(),
+        );
+
+        assert!(producer.join().unwrap());
+        assert_eq!(changes, Changes::default());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], Error::Write { .. }));
+
+        // Clean up
+        fs::remove_file(blocker).unwrap();
+    }
+
+    #[test]
+    fn test_write_keeps_files_of_failed_conversations() {
+        let output_folder = PathBuf::from("./test_output_failed");
+        fs::create_dir_all(output_folder.join("2022")).unwrap();
+        fs::write(output_folder.join("2022/2022-12-01-Blocked.md"), "").unwrap();
+        let mut manifest = Manifest::default();
+        manifest.record(
+            "2022/2022-12-01-Blocked.md".to_string(),
+            ManifestEntry {
+                id: Some("c1".to_string()),
+                update_time: 0.0,
+                hash: content_hash(b""),
+            },
+        );
+        manifest.save(&output_folder).unwrap();
+        let mut manifest = Manifest::load(&output_folder).unwrap();
+        // A file standing where the folder of the conversation should be created.
+        fs::write(output_folder.join("2023"), "").unwrap();
+        let mut conversation =
+            Conversation::new("Blocked".to_string(), Vec::new(), "2023-02-01".to_string());
+        conversation.id = Some("c1".to_string());
+        conversation.update_time = 1675209600.0;
+
+        let (_, errors) = write_all(
+            vec![conversation],
+            &output_folder,
+            &[OutputFormat::Markdown.exporter(&ExportOptions::default())],
+            Layout::new("{year}", DateFormat::default()).unwrap(),
+            &mut manifest,
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert!(manifest.prune(&output_folder).unwrap().is_empty());
+        assert!(output_folder.join("2022/2022-12-01-Blocked.md").exists());
+
+        // Clean up
+        fs::remove_dir_all(output_folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_file_stays_in_output_folder() {
+        let output_folder = PathBuf::from("./test_output_outside");
+        let entry = ManifestEntry {
+            id: None,
+            update_time: 0.0,
+            hash: content_hash(b"escaped"),
+        };
+
+        for filename in ["../test_output_escaped.md", "/tmp/test_output_escaped.md"] {
+            let result = write_file(
+                &output_folder,
+                filename,
+                b"escaped",
+                &entry,
+                &Manifest::default(),
+            );
+            assert!(matches!(result, Err(Error::Write { .. })), "{}", filename);
+        }
+        assert!(!Path::new("./test_output_escaped.md").exists());
+        assert!(!Path::new("/tmp/test_output_escaped.md").exists());
+        assert!(!output_folder.exists());
+    }
 }
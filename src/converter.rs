@@ -1,36 +1,357 @@
 use crate::{
-    model::{Conversation, ConversationItem, GPTInteraction, Node, Part},
+    citations::{cite_sources, number_citations},
+    importer::{ImportOptions, Importer},
+    model::{
+        Branch, Conversation, ConversationItem, GPTInteraction, Image, ItemKind, Message, Node,
+        Part,
+    },
     utils::date_from_epoch_time,
 };
+use log::debug;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 
-/// Creates a `Conversation` object from a given `GPTInteraction` by processing its mapping of conversation nodes.
+/// `Importer` for the `conversations.json` file of a ChatGPT data export.
+///
+/// ChatGPT conversations are recognized by their `mapping` field, which holds the tree of
+/// messages deserialized as a `GPTInteraction`.
+pub struct ChatGptImporter;
+
+impl Importer for ChatGptImporter {
+    fn name(&self) -> &'static str {
+        "chatgpt"
+    }
+
+    fn detect(&self, conversation: &Value) -> bool {
+        conversation.get("mapping").is_some_and(Value::is_object)
+    }
+
+    fn import(
+        &self,
+        conversation: Value,
+        options: &ImportOptions,
+    ) -> Result<Vec<Conversation>, serde_json::Error> {
+        let interaction: GPTInteraction = serde_json::from_value(conversation)?;
+        Ok(if options.all_branches {
+            create_conversation_branches_from(interaction, options)
+        } else {
+            vec![create_conversation_from(interaction, options)]
+        })
+    }
+}
+
+/// Creates a `Conversation` object from a given `GPTInteraction` by processing its mapping of
+/// conversation nodes.
+///
+/// Only the thread the user actually ended on is kept: nodes belonging to abandoned branches
+/// (edited prompts, regenerated answers) are left out. See `current_thread` for how this thread
+/// is selected.
 ///
 /// # Arguments
 ///
 /// * `gpt_interaction` - A `GPTInteraction` object containing a mapping of nodes, each representing
-///   parts of the conversation hierarchy. The interaction includes a title and timestamps associated
-///   with its creation and update events.
+///   parts of the conversation hierarchy. The interaction includes a title and timestamps
+///   associated with its creation and update events.
+///
+/// * `options` - The `ImportOptions` of the export, telling whether the reasoning of the answers
+///   and the system messages are kept.
 ///
 /// # Returns
 ///
 /// A `Conversation` object that includes:
-/// - A sorted list of `ConversationItem` objects, each derived from the nodes in the `GPTInteraction`.
+/// - A list of `ConversationItem` objects, in thread order from the root to the current node,
+///   after the system messages (see `hoist_system_messages`). The sources cited by the answers
+///   are numbered across the thread (see `number_citations`), and reasoning recaps are attached
+///   to the reasoning they follow (see `attach_reasoning_recaps`).
 /// - The title of the conversation, which is extracted directly from the `GPTInteraction`.
 /// - The most recent update date, derived from the `update_time` field of the `GPTInteraction`.
-pub fn create_conversation_from(gpt_interaction: GPTInteraction) -> Conversation {
-    let mut conversation_items: Vec<ConversationItem> = Vec::new();
+/// - Its identifier, creation and update times, custom GPT identifier, and the model that wrote
+///   the last answer of the thread (see `thread_model`), for the front matter.
+/// - Whether the conversation was archived, for filtering.
+pub(crate) fn create_conversation_from(
+    gpt_interaction: GPTInteraction,
+    options: &ImportOptions,
+) -> Conversation {
+    let GPTInteraction {
+        id,
+        conversation_id,
+        title,
+        create_time,
+        update_time,
+        mapping,
+        current_node,
+        default_model_slug,
+        gizmo_id,
+        is_archived,
+    } = gpt_interaction;
+
+    let thread = current_thread(&mapping, current_node.as_deref());
+    let mut conversation_items = attach_reasoning_recaps(
+        thread
+            .iter()
+            .filter_map(|id| mapping.get(id))
+            .filter_map(|node| process_interaction_node(node, &title, options))
+            .collect(),
+    );
+    hoist_system_messages(&mut conversation_items);
+    number_citations(&mut conversation_items);
+    debug!(
+        "Conversation '{}': {} items from the {} nodes of the current thread, {} nodes of other branches left out",
+        title,
+        conversation_items.len(),
+        thread.len(),
+        mapping.len() - thread.len()
+    );
+
+    let mut conversation =
+        Conversation::new(title, conversation_items, date_from_epoch_time(update_time));
+    conversation.id = id.or(conversation_id);
+    conversation.create_time = create_time;
+    conversation.update_time = update_time;
+    conversation.model = thread_model(&mapping, &thread).or(default_model_slug);
+    conversation.gizmo_id = gizmo_id;
+    conversation.archived = is_archived.unwrap_or(false);
+    conversation
+}
+
+/// Creates one `Conversation` per branch of the conversation tree of a `GPTInteraction`.
+///
+/// Every path from the root to a leaf of the mapping is a branch: editing a prompt or
+/// regenerating an answer creates a sibling node, and therefore a new branch. Branches are
+/// numbered in the order their leaves are reached by a depth-first walk along `Node.children`,
+/// which keeps the numbering identical from one run to the next.
+///
+/// # Arguments
+///
+/// * `gpt_interaction` - A `GPTInteraction` object containing the conversation tree.
+///
+/// * `options` - The `ImportOptions` of the export, telling whether the reasoning of the answers
+///   and the system messages are kept.
+///
+/// # Returns
+///
+/// A `Vec<Conversation>` with one entry per branch holding at least one message. Each entry
+/// carries its `Branch` position, except when the conversation has a single branch, in which
+/// case the result is equivalent to `create_conversation_from`.
+pub(crate) fn create_conversation_branches_from(
+    gpt_interaction: GPTInteraction,
+    options: &ImportOptions,
+) -> Vec<Conversation> {
+    let GPTInteraction {
+        id,
+        conversation_id,
+        title,
+        create_time,
+        update_time,
+        mapping,
+        default_model_slug,
+        gizmo_id,
+        is_archived,
+        ..
+    } = gpt_interaction;
+    let id = id.or(conversation_id);
+
+    let items: HashMap<&str, ConversationItem> = mapping
+        .iter()
+        .filter_map(|(id, node)| {
+            Some((
+                id.as_str(),
+                process_interaction_node(node, &title, options)?,
+            ))
+        })
+        .collect();
+    let branches: Vec<(Vec<ConversationItem>, Option<String>)> = leaf_threads(&mapping)
+        .iter()
+        .map(|thread| {
+            let mut branch = attach_reasoning_recaps(
+                thread
+                    .iter()
+                    .filter_map(|id| items.get(id.as_str()).cloned())
+                    .collect(),
+            );
+            hoist_system_messages(&mut branch);
+            number_citations(&mut branch);
+            (branch, thread_model(&mapping, thread))
+        })
+        .filter(|(branch, _)| !branch.is_empty())
+        .collect();
+
+    let count = branches.len();
+    debug!(
+        "Conversation '{}': {} items from {} nodes, in {} branches",
+        title,
+        items.len(),
+        mapping.len(),
+        count
+    );
+    let date = date_from_epoch_time(update_time);
+    branches
+        .into_iter()
+        .enumerate()
+        .map(|(index, (branch, model))| {
+            let mut conversation = Conversation::new(title.clone(), branch, date.clone());
+            conversation.id = id.clone();
+            conversation.create_time = create_time;
+            conversation.update_time = update_time;
+            conversation.model = model.or_else(|| default_model_slug.clone());
+            conversation.gizmo_id = gizmo_id.clone();
+            conversation.archived = is_archived.unwrap_or(false);
+            if count > 1 {
+                conversation.branch = Some(Branch::new(index + 1, count));
+            }
+            conversation
+        })
+        .collect()
+}
+
+/// Moves the recap of each reasoning, e.g. "Thought for 12 seconds", to the reasoning item it
+/// follows. Recaps of reasoning missing from the export are left out.
+fn attach_reasoning_recaps(items: Vec<ConversationItem>) -> Vec<ConversationItem> {
+    let mut attached: Vec<ConversationItem> = Vec::with_capacity(items.len());
+    for item in items {
+        match (&item.kind, attached.last_mut()) {
+            (ItemKind::Reasoning { recap }, Some(previous))
+                if item.text.is_empty()
+                    && previous.kind == (ItemKind::Reasoning { recap: None }) =>
+            {
+                previous.kind = ItemKind::Reasoning {
+                    recap: recap.clone(),
+                };
+            }
+            (ItemKind::Reasoning { .. }, _) if item.text.is_empty() => {}
+            _ => attached.push(item),
+        }
+    }
+    attached
+}
 
-    for object in gpt_interaction.mapping {
-        if let Some(item) = process_interaction_node(object.1) {
-            conversation_items.push(item);
+/// Returns the slug of the model that wrote the last message of a thread recording one, which
+/// reflects a model switched to during the conversation better than its `default_model_slug`.
+fn thread_model(mapping: &HashMap<String, Node>, thread: &[String]) -> Option<String> {
+    thread
+        .iter()
+        .rev()
+        .filter_map(|id| mapping.get(id)?.message.as_ref())
+        .find_map(model_slug)
+}
+
+/// Returns the slug of the model that wrote a message, recorded in its metadata.
+fn model_slug(message: &Message) -> Option<String> {
+    message
+        .metadata
+        .additional_metadata
+        .get("model_slug")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Lists every root-to-leaf path of a conversation tree.
+///
+/// Roots are the nodes without a parent in the mapping; they are visited in identifier order,
+/// and children in the order of `Node.children`. Nodes reachable through several paths of a
+/// malformed mapping are only visited once.
+///
+/// # Returns
+///
+/// A `Vec<Vec<String>>` holding, for each leaf, the node identifiers from the root to that leaf.
+fn leaf_threads(mapping: &HashMap<String, Node>) -> Vec<Vec<String>> {
+    let mut roots: Vec<&str> = mapping
+        .iter()
+        .filter(|(_, node)| {
+            node.parent
+                .as_ref()
+                .is_none_or(|parent| !mapping.contains_key(parent))
+        })
+        .map(|(id, _)| id.as_str())
+        .collect();
+    roots.sort_unstable();
+
+    // The walk keeps its own stack, as threads can be far deeper than the call stack allows.
+    // Each entry holds a node and its depth, to which the path is cut back when it is visited.
+    let mut stack: Vec<(&str, usize)> = roots.into_iter().rev().map(|root| (root, 0)).collect();
+    let mut path: Vec<String> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut threads = Vec::new();
+    while let Some((id, depth)) = stack.pop() {
+        let Some(node) = mapping.get(id) else {
+            continue;
+        };
+        if !visited.insert(id) {
+            continue;
+        }
+        path.truncate(depth);
+        path.push(id.to_string());
+        let children: Vec<&str> = node
+            .children
+            .iter()
+            .map(String::as_str)
+            .filter(|child| mapping.contains_key(*child))
+            .collect();
+        if children.is_empty() {
+            threads.push(path.clone());
         }
+        // Pushed in reverse so that the first child is visited first.
+        stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
     }
-    conversation_items.sort_by(|item1, item2| item1.time.total_cmp(&item2.time));
-    Conversation::new(
-        gpt_interaction.title,
-        conversation_items,
-        date_from_epoch_time(gpt_interaction.update_time),
-    )
+    threads
+}
+
+/// Selects the thread of a conversation tree that the user ended on.
+///
+/// The thread ends at `current_node` when it is present in the mapping. Older exports do not
+/// carry this field, in which case the most recently created leaf is used instead. The thread
+/// is then rebuilt by following the `parent` links up to the root.
+///
+/// # Arguments
+///
+/// * `mapping` - The nodes of the conversation, keyed by their identifier.
+///
+/// * `current_node` - The identifier of the last node displayed to the user, if known.
+///
+/// # Returns
+///
+/// A `Vec<String>` holding the node identifiers of the thread, ordered from the root to the leaf.
+/// A malformed mapping containing a parent cycle stops the walk once every node has been visited.
+fn current_thread(mapping: &HashMap<String, Node>, current_node: Option<&str>) -> Vec<String> {
+    let mut thread = Vec::new();
+    let mut next = current_node
+        .filter(|id| mapping.contains_key(*id))
+        .or_else(|| latest_leaf(mapping));
+
+    while let Some(id) = next {
+        let Some(node) = mapping.get(id) else {
+            break;
+        };
+        if thread.len() == mapping.len() {
+            break;
+        }
+        thread.push(id.to_string());
+        next = node.parent.as_deref();
+    }
+    thread.reverse();
+    thread
+}
+
+/// Returns the identifier of the leaf node whose message was created last.
+///
+/// Ties, including leaves without a message, are broken on the node identifier so that the
+/// selected leaf does not depend on the iteration order of the mapping.
+fn latest_leaf(mapping: &HashMap<String, Node>) -> Option<&str> {
+    let created = |node: &Node| {
+        node.message
+            .as_ref()
+            .and_then(|message| message.create_time)
+            .unwrap_or(0.0)
+    };
+    mapping
+        .iter()
+        .filter(|(_, node)| node.children.is_empty())
+        .max_by(|(id1, node1), (id2, node2)| {
+            created(node1)
+                .total_cmp(&created(node2))
+                .then_with(|| id1.cmp(id2))
+        })
+        .map(|(id, _)| id.as_str())
 }
 
 /// Processes a `Node` object and extracts a `ConversationItem` if applicable.
@@ -42,29 +363,107 @@ pub fn create_conversation_from(gpt_interaction: GPTInteraction) -> Conversation
 ///
 /// # Arguments
 ///
-/// * `node` - A `Node` object that potentially contains a `Message`. The function examines this
-///   `Node` to determine if it can produce a valid `ConversationItem`.
+/// * `node` - A reference to a `Node` object that potentially contains a `Message`. The function
+///   examines this `Node` to determine if it can produce a valid `ConversationItem`.
+///
+/// * `title` - The title of the conversation, naming it in the debug messages that tell why a node
+///   was skipped.
 ///
 /// # Returns
 ///
-/// An `Option<ConversationItem>`. The function returns `Some(ConversationItem)` if the node contains
-/// a valid message authored by an "assistant" or "user" with non-empty text content. Otherwise, it
-/// returns `None`.
+/// An `Option<ConversationItem>`. The function returns `Some(ConversationItem)` if the node
+/// contains a valid message authored by an "assistant" or "user" with non-empty text content.
+/// Otherwise, it returns `None`.
 ///
 /// # Details
 ///
-/// - The `message` field of the node is required to be present; if absent, the function returns `None`.
-/// - The role of the message author must be either "assistant" or "user" for the message to be processed.
-/// - The content of the message must contain text parts. These are filtered to include only string parts,
-///   ignoring any non-string parts. The resulting strings are concatenated, and if the resulting text is
-///   empty or only whitespace, the function returns `None`.
-fn process_interaction_node(node: Node) -> Option<ConversationItem> {
-    let message = node.message?;
-    let role = message.author.role;
-    if role != "assistant" && role != "user" {
+/// - The `message` field of the node is required to be present; if absent, the function returns
+///   `None`.
+/// - Code sent to the code interpreter and the output of its execution are handed to
+///   `process_tool_message`.
+/// - The role of the message author must be either "assistant" or "user" for the message to be
+///   processed. Tool messages holding images, such as the pictures generated by DALL·E, are
+///   processed as well and attributed to the tool, and system messages become items of kind
+///   `ItemKind::SystemPrompt`. The custom instructions of the user become an item of kind
+///   `ItemKind::CustomInstructions` (see `process_custom_instructions`). Both are hidden from
+///   the conversation in ChatGPT, and are left out unless `options.system_messages` is set.
+/// - The content of the message must contain parts. String parts are concatenated into the text of
+///   the item, and image parts become `Image`s of the item (see `image_from_part`); other parts are
+///   ignored. If the resulting text is empty or only whitespace and there is no image, the function
+///   returns `None`.
+/// - Assistant items carry the slug of the model that wrote them, when the message metadata records
+///   it.
+/// - Every item carries the id of its message.
+/// - The reasoning of models such as o1 and o3 becomes an item of kind `ItemKind::Reasoning` (see
+///   `process_reasoning_message`), if `options.reasoning` is set. It is left out otherwise.
+/// - The citation markers of assistant messages are replaced with footnote references to the
+///   `Citation`s of the item (see `cite_sources`), numbered from 1 within the message until
+///   `number_citations` numbers them across the conversation. Web browsing results and quotes
+///   are tool messages without text parts, whose pages are reached through these citations.
+fn process_interaction_node(
+    node: &Node,
+    title: &str,
+    options: &ImportOptions,
+) -> Option<ConversationItem> {
+    let Some(message) = node.message.as_ref() else {
+        debug!(
+            "Conversation '{}': skipped node {} without message",
+            title, node.id
+        );
         return None;
+    };
+    if let Some(mut item) = process_tool_message(message) {
+        item.id = Some(message.id.clone());
+        return Some(item);
+    }
+    if let Some(mut item) = process_reasoning_message(message) {
+        if !options.reasoning {
+            debug!(
+                "Conversation '{}': skipped reasoning message {}",
+                title, node.id
+            );
+            return None;
+        }
+        item.id = Some(message.id.clone());
+        item.model = model_slug(message);
+        return Some(item);
     }
-    let content_parts = message.content.parts?;
+    if let Some(mut item) = process_custom_instructions(message) {
+        if !options.system_messages {
+            debug!(
+                "Conversation '{}': skipped custom instructions {}",
+                title, node.id
+            );
+            return None;
+        }
+        item.id = Some(message.id.clone());
+        return Some(item);
+    }
+    let role = message.author.role.as_str();
+    let Some(content_parts) = message.content.parts.as_ref() else {
+        debug!(
+            "Conversation '{}': skipped {} message {} of content type '{}' without text parts",
+            title, role, node.id, message.content.content_type
+        );
+        return None;
+    };
+    let images: Vec<Image> = content_parts.iter().filter_map(image_from_part).collect();
+    let author = match message.author.role.as_str() {
+        "assistant" | "user" => message.author.role.clone(),
+        "system" if options.system_messages => message.author.role.clone(),
+        "tool" if !images.is_empty() => message
+            .author
+            .name
+            .clone()
+            .unwrap_or_else(|| "tool".to_string()),
+        _ => {
+            debug!(
+                "Conversation '{}': skipped {} message {}",
+                title, role, node.id
+            );
+            return None;
+        }
+    };
     let text = content_parts
         .iter()
         .filter_map(|part| match part {
@@ -73,27 +472,204 @@ fn process_interaction_node(node: Node) -> Option<ConversationItem> {
         })
         .collect::<Vec<_>>()
         .join("\n");
-    if text.trim().is_empty() {
+    if text.trim().is_empty() && images.is_empty() {
+        debug!(
+            "Conversation '{}': skipped empty {} message {}",
+            title, role, node.id
+        );
         return None;
     }
-    Some(ConversationItem::new(
+    let (text, citations) = if message.author.role == "assistant" {
+        cite_sources(&text, &message.metadata)
+    } else {
+        (text, Vec::new())
+    };
+    let mut item = ConversationItem::new(text, author, message.create_time.unwrap_or(0.0));
+    item.id = Some(message.id.clone());
+    item.images = images;
+    item.citations = citations;
+    if message.author.role == "assistant" {
+        item.model = model_slug(message);
+    }
+    if message.author.role == "system" {
+        item.kind = ItemKind::SystemPrompt;
+    }
+    Some(item)
+}
+
+/// Moves the system prompts and custom instructions to the start of the items of a thread, in
+/// their order, since ChatGPT gives them to the assistant before the conversation whatever their
+/// position in the thread.
+fn hoist_system_messages(items: &mut [ConversationItem]) {
+    items.sort_by_key(|item| {
+        !matches!(
+            item.kind,
+            ItemKind::SystemPrompt | ItemKind::CustomInstructions
+        )
+    });
+}
+
+/// Extracts an `Image` from an "image_asset_pointer" part of a multimodal message.
+///
+/// The asset pointer is a URL whose path is the id of the file in the export, such as
+/// `file-service://file-AbC123` or `sediment://file_00000000abc`.
+fn image_from_part(part: &Part) -> Option<Image> {
+    let Part::Object(object) = part else {
+        return None;
+    };
+    if object.get("content_type")?.as_str()? != "image_asset_pointer" {
+        return None;
+    }
+    let pointer = object.get("asset_pointer")?.as_str()?;
+    let file_id = pointer.rsplit("://").next()?;
+    if file_id.is_empty() {
+        return None;
+    }
+    Some(Image::new(file_id.to_string()))
+}
+
+/// Extracts a `ConversationItem` of kind `ItemKind::Reasoning` from the reasoning of models
+/// such as o1 and o3, if the message holds some.
+///
+/// "thoughts" content becomes the text of the item, each step as its bold summary followed by
+/// its content. "reasoning_recap" content becomes an item without text holding the recap, to be
+/// attached to the reasoning it follows (see `attach_reasoning_recaps`).
+///
+/// # Returns
+///
+/// `Some(ConversationItem)` attributed to the assistant for such messages, `None` otherwise or
+/// when the reasoning is empty.
+fn process_reasoning_message(message: &Message) -> Option<ConversationItem> {
+    let (text, recap) = match message.content.content_type.as_str() {
+        "thoughts" => {
+            let steps: Vec<String> = message
+                .content
+                .thoughts
+                .iter()
+                .flatten()
+                .filter(|thought| !thought.content.trim().is_empty())
+                .map(|thought| match thought.summary.trim() {
+                    "" => thought.content.trim().to_string(),
+                    summary => format!("**{}**\n\n{}", summary, thought.content.trim()),
+                })
+                .collect();
+            (steps.join("\n\n"), None)
+        }
+        "reasoning_recap" => (String::new(), message.content.content.clone()),
+        _ => return None,
+    };
+    if text.is_empty() && recap.as_deref().is_none_or(|recap| recap.trim().is_empty()) {
+        return None;
+    }
+    let mut item = ConversationItem::new(
+        text,
+        "assistant".to_string(),
+        message.create_time.unwrap_or(0.0),
+    );
+    item.kind = ItemKind::Reasoning { recap };
+    Some(item)
+}
+
+/// Extracts a `ConversationItem` of kind `ItemKind::CustomInstructions` from the
+/// "user_editable_context" content ChatGPT adds at the start of conversations, if the message
+/// holds some.
+///
+/// The profile and the response instructions of the user are kept as exported, including the
+/// preamble ChatGPT prepends to them, and attributed to "system" since the user did not send
+/// them in the conversation.
+fn process_custom_instructions(message: &Message) -> Option<ConversationItem> {
+    if message.content.content_type != "user_editable_context" {
+        return None;
+    }
+    let text = [
+        &message.content.user_profile,
+        &message.content.user_instructions,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|text| text.trim())
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n\n");
+    if text.is_empty() {
+        return None;
+    }
+    let mut item = ConversationItem::new(
         text,
-        role,
+        "system".to_string(),
         message.create_time.unwrap_or(0.0),
-    ))
+    );
+    item.kind = ItemKind::CustomInstructions;
+    Some(item)
+}
+
+/// Extracts a `ConversationItem` from a code interpreter message, if the message is one.
+///
+/// Two kinds of messages are recognized:
+/// - "code" content written by the assistant for the "python" tool, attributed to that tool (its
+///   `recipient`). The language defaults to the name of the tool when the export says "unknown".
+///   The commands sent as "code" to other tools, such as the browser or DALL·E, are not code
+///   and are left out.
+/// - "execution_output" content written by a tool, attributed to the tool by its author name.
+///
+/// # Returns
+///
+/// `Some(ConversationItem)` of kind `ItemKind::Code` or `ItemKind::ExecutionOutput` for such
+/// messages with a non-empty text, `None` otherwise.
+fn process_tool_message(message: &Message) -> Option<ConversationItem> {
+    let (author, kind) = match (
+        message.author.role.as_str(),
+        message.content.content_type.as_str(),
+    ) {
+        ("assistant", "code") if message.recipient == "python" => {
+            let language = message
+                .content
+                .language
+                .clone()
+                .filter(|language| language != "unknown")
+                .or_else(|| Some(message.recipient.clone()));
+            (message.recipient.clone(), ItemKind::Code { language })
+        }
+        ("tool", "execution_output") => (
+            message
+                .author
+                .name
+                .clone()
+                .unwrap_or_else(|| "tool".to_string()),
+            ItemKind::ExecutionOutput,
+        ),
+        _ => return None,
+    };
+    let text = message.content.text.as_deref()?;
+    if text.trim().is_empty() {
+        return None;
+    }
+    let mut item =
+        ConversationItem::new(text.to_string(), author, message.create_time.unwrap_or(0.0));
+    item.kind = kind;
+    Some(item)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        converter::create_conversation_from,
-        model::{Author, Content, GPTInteraction, Message, MessageMetadata, Node, Part},
+        converter::{
+            create_conversation_branches_from, create_conversation_from, leaf_threads,
+            ChatGptImporter,
+        },
+        importer::{ImportOptions, Importer},
+        model::{
+            Author, Branch, Content, GPTInteraction, Image, ItemKind, Message, MessageMetadata,
+            Node, Part, Thought,
+        },
     };
     use std::collections::HashMap;
 
     #[test]
     fn test_create_conversation_from() {
         let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
             title: "Test Conversation".to_string(),
             create_time: 0.0,
             update_time: 1672531200.0,
@@ -114,6 +690,12 @@ mod tests {
                             content: Content {
                                 content_type: "text".to_string(),
                                 parts: Some(vec![Part::String("Hello!".to_string())]),
+                                text: None,
+                                language: None,
+                                thoughts: None,
+                                content: None,
+                                user_profile: None,
+                                user_instructions: None,
                             },
                             status: "complete".to_string(),
                             end_turn: None,
@@ -144,6 +726,12 @@ mod tests {
                             content: Content {
                                 content_type: "text".to_string(),
                                 parts: Some(vec![Part::String("Hi!".to_string())]),
+                                text: None,
+                                language: None,
+                                thoughts: None,
+                                content: None,
+                                user_profile: None,
+                                user_instructions: None,
                             },
                             status: "complete".to_string(),
                             end_turn: None,
@@ -159,9 +747,13 @@ mod tests {
                     },
                 ),
             ]),
+            current_node: None,
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
         };
 
-        let conversation = create_conversation_from(interaction);
+        let conversation = create_conversation_from(interaction, &ImportOptions::default());
 
         assert_eq!(conversation.title, "Test Conversation");
         assert_eq!(conversation.date, "2023-01-01");
@@ -170,5 +762,608 @@ mod tests {
         assert_eq!(conversation.items[0].author, "user");
         assert_eq!(conversation.items[1].text, "Hi!");
         assert_eq!(conversation.items[1].author, "assistant");
+        assert_eq!(conversation.items[1].id.as_deref(), Some("2"));
+    }
+
+    fn node(id: &str, parent: Option<&str>, children: &[&str], role: &str, text: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            message: Some(Message {
+                id: id.to_string(),
+                author: Author {
+                    role: role.to_string(),
+                    name: None,
+                    metadata: HashMap::new(),
+                },
+                create_time: id.parse().ok(),
+                update_time: None,
+                content: Content {
+                    content_type: "text".to_string(),
+                    parts: Some(vec![Part::String(text.to_string())]),
+                    text: None,
+                    language: None,
+                    thoughts: None,
+                    content: None,
+                    user_profile: None,
+                    user_instructions: None,
+                },
+                status: "finished_successfully".to_string(),
+                end_turn: None,
+                weight: 1.0,
+                metadata: MessageMetadata {
+                    additional_metadata: HashMap::new(),
+                },
+                recipient: "all".to_string(),
+                channel: None,
+            }),
+            parent: parent.map(str::to_string),
+            children: children.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// A prompt ("1") answered twice ("2" then the regenerated "3"), the second answer being
+    /// followed up by "4". The first answer is newer than "3" to make time ordering misleading.
+    fn branching_interaction(current_node: Option<&str>) -> GPTInteraction {
+        let mut abandoned = node("2", Some("1"), &[], "assistant", "First answer");
+        abandoned.message.as_mut().unwrap().create_time = Some(3.5);
+        GPTInteraction {
+            id: Some("branching".to_string()),
+            conversation_id: Some("branching".to_string()),
+            title: "Branching".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([
+                (
+                    "1".to_string(),
+                    node("1", None, &["2", "3"], "user", "Question"),
+                ),
+                ("2".to_string(), abandoned),
+                (
+                    "3".to_string(),
+                    node("3", Some("1"), &["4"], "assistant", "Second answer"),
+                ),
+                (
+                    "4".to_string(),
+                    node("4", Some("3"), &[], "user", "Follow-up"),
+                ),
+            ]),
+            current_node: current_node.map(str::to_string),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        }
+    }
+
+    fn texts(conversation: &crate::model::Conversation) -> Vec<&str> {
+        conversation
+            .items
+            .iter()
+            .map(|item| item.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_create_conversation_from_follows_current_node() {
+        let conversation =
+            create_conversation_from(branching_interaction(Some("4")), &ImportOptions::default());
+        assert_eq!(
+            texts(&conversation),
+            vec!["Question", "Second answer", "Follow-up"]
+        );
+
+        let conversation =
+            create_conversation_from(branching_interaction(Some("2")), &ImportOptions::default());
+        assert_eq!(texts(&conversation), vec!["Question", "First answer"]);
+        assert_eq!(conversation.id.as_deref(), Some("branching"));
+    }
+
+    #[test]
+    fn test_create_conversation_from_without_current_node_uses_latest_leaf() {
+        let conversation =
+            create_conversation_from(branching_interaction(None), &ImportOptions::default());
+        assert_eq!(
+            texts(&conversation),
+            vec!["Question", "Second answer", "Follow-up"]
+        );
+
+        let conversation = create_conversation_from(
+            branching_interaction(Some("unknown")),
+            &ImportOptions::default(),
+        );
+        assert_eq!(conversation.items.len(), 3);
+    }
+
+    #[test]
+    fn test_create_conversation_branches_from() {
+        let branches = create_conversation_branches_from(
+            branching_interaction(Some("4")),
+            &ImportOptions::default(),
+        );
+
+        assert_eq!(branches.len(), 2);
+        assert_eq!(texts(&branches[0]), vec!["Question", "First answer"]);
+        assert_eq!(branches[0].branch, Some(Branch::new(1, 2)));
+        assert_eq!(branches[0].id.as_deref(), Some("branching"));
+        assert_eq!(
+            texts(&branches[1]),
+            vec!["Question", "Second answer", "Follow-up"]
+        );
+        assert_eq!(branches[1].branch, Some(Branch::new(2, 2)));
+    }
+
+    #[test]
+    fn test_leaf_threads_of_long_conversation() {
+        let ids: Vec<String> = (0..10_000).map(|i| i.to_string()).collect();
+        let mut mapping: HashMap<String, Node> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let parent = i.checked_sub(1).map(|parent| ids[parent].as_str());
+                let children: Vec<&str> = ids.get(i + 1).map(String::as_str).into_iter().collect();
+                (id.clone(), node(id, parent, &children, "user", "Hi"))
+            })
+            .collect();
+        mapping
+            .get_mut("0")
+            .unwrap()
+            .children
+            .push("last".to_string());
+        mapping.insert(
+            "last".to_string(),
+            node("last", Some("0"), &[], "user", "Bye"),
+        );
+
+        let threads = leaf_threads(&mapping);
+
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0], ids);
+        assert_eq!(threads[1], vec!["0", "last"]);
+    }
+
+    #[test]
+    fn test_create_conversation_branches_from_single_branch() {
+        let mut interaction = branching_interaction(None);
+        interaction.mapping.remove("2");
+        interaction.mapping.get_mut("1").unwrap().children = vec!["3".to_string()];
+
+        let branches = create_conversation_branches_from(interaction, &ImportOptions::default());
+
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].branch, None);
+        assert_eq!(branches[0].items.len(), 3);
+    }
+
+    #[test]
+    fn test_create_conversation_from_citations() {
+        let cite = |node: &mut Node, text: &str, url: &str| {
+            let message = node.message.as_mut().unwrap();
+            message.content.parts = Some(vec![Part::String(format!("{}【0†source】", text))]);
+            message.metadata.additional_metadata.insert(
+                "citations".to_string(),
+                serde_json::json!([{
+                    "start_ix": text.chars().count(),
+                    "end_ix": text.chars().count() + 10,
+                    "metadata": {"title": "Source", "url": url}
+                }]),
+            );
+        };
+        let mut interaction = branching_interaction(Some("5"));
+        cite(
+            interaction.mapping.get_mut("3").unwrap(),
+            "Second answer",
+            "https://a.example/",
+        );
+        interaction.mapping.get_mut("4").unwrap().children = vec!["5".to_string()];
+        let mut last = node("5", Some("4"), &[], "assistant", "");
+        cite(&mut last, "Last answer", "https://b.example/");
+        interaction.mapping.insert("5".to_string(), last);
+
+        let conversation = create_conversation_from(interaction, &ImportOptions::default());
+
+        assert_eq!(
+            texts(&conversation),
+            vec![
+                "Question",
+                "Second answer[^1]",
+                "Follow-up",
+                "Last answer[^2]"
+            ]
+        );
+        assert_eq!(conversation.items[3].citations[0].number, 2);
+        assert_eq!(conversation.items[3].citations[0].url, "https://b.example/");
+    }
+
+    #[test]
+    fn test_create_conversation_from_reasoning() {
+        let mut thoughts = node("2", Some("1"), &["3"], "assistant", "");
+        thoughts.message.as_mut().unwrap().content = Content {
+            content_type: "thoughts".to_string(),
+            parts: None,
+            text: None,
+            language: None,
+            thoughts: Some(vec![
+                Thought {
+                    summary: "Recalling the release".to_string(),
+                    content: "Rust 1.0 shipped in May 2015.".to_string(),
+                },
+                Thought {
+                    summary: String::new(),
+                    content: "That answers it.".to_string(),
+                },
+            ]),
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let mut recap = node("3", Some("2"), &["4"], "assistant", "");
+        recap.message.as_mut().unwrap().content = Content {
+            content_type: "reasoning_recap".to_string(),
+            parts: None,
+            text: None,
+            language: None,
+            thoughts: None,
+            content: Some("Thought for 5 seconds".to_string()),
+            user_profile: None,
+            user_instructions: None,
+        };
+        let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
+            title: "Reasoning".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([
+                (
+                    "1".to_string(),
+                    node("1", None, &["2"], "user", "When was Rust 1.0 released?"),
+                ),
+                ("2".to_string(), thoughts),
+                ("3".to_string(), recap),
+                (
+                    "4".to_string(),
+                    node("4", Some("3"), &[], "assistant", "In May 2015."),
+                ),
+            ]),
+            current_node: Some("4".to_string()),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        };
+
+        let without_reasoning = ChatGptImporter
+            .import(
+                serde_json::to_value(&interaction).unwrap(),
+                &ImportOptions::default(),
+            )
+            .unwrap();
+        let options = ImportOptions {
+            reasoning: true,
+            ..ImportOptions::default()
+        };
+        let conversation = create_conversation_from(interaction, &options);
+
+        assert_eq!(conversation.items.len(), 3);
+        let reasoning = &conversation.items[1];
+        assert_eq!(
+            reasoning.kind,
+            ItemKind::Reasoning {
+                recap: Some("Thought for 5 seconds".to_string())
+            }
+        );
+        assert_eq!(
+            reasoning.text,
+            "**Recalling the release**\n\nRust 1.0 shipped in May 2015.\n\nThat answers it."
+        );
+        assert_eq!(reasoning.author, "assistant");
+        assert_eq!(reasoning.id.as_deref(), Some("2"));
+
+        assert_eq!(
+            texts(&without_reasoning[0]),
+            vec!["When was Rust 1.0 released?", "In May 2015."]
+        );
+    }
+
+    #[test]
+    fn test_create_conversation_from_system_messages() {
+        let mut instructions = node("3", Some("2"), &["4"], "user", "");
+        instructions.message.as_mut().unwrap().content = Content {
+            content_type: "user_editable_context".to_string(),
+            parts: None,
+            text: None,
+            language: None,
+            thoughts: None,
+            content: None,
+            user_profile: Some("User profile:\n```I write Rust.```".to_string()),
+            user_instructions: Some("User's Instructions:\n```Be concise.```\n".to_string()),
+        };
+        let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
+            title: "Instructions".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([
+                (
+                    "1".to_string(),
+                    node("1", None, &["2"], "system", "You are ChatGPT."),
+                ),
+                ("2".to_string(), node("2", Some("1"), &["3"], "user", "Hi")),
+                ("3".to_string(), instructions),
+                (
+                    "4".to_string(),
+                    node("4", Some("3"), &[], "assistant", "Hello."),
+                ),
+            ]),
+            current_node: Some("4".to_string()),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        };
+
+        let without_system_messages = ChatGptImporter
+            .import(
+                serde_json::to_value(&interaction).unwrap(),
+                &ImportOptions::default(),
+            )
+            .unwrap();
+        let options = ImportOptions {
+            system_messages: true,
+            ..ImportOptions::default()
+        };
+        let conversation = create_conversation_from(interaction, &options);
+
+        assert_eq!(conversation.items.len(), 4);
+        assert_eq!(conversation.items[0].kind, ItemKind::SystemPrompt);
+        assert_eq!(conversation.items[0].author, "system");
+        assert_eq!(conversation.items[1].kind, ItemKind::CustomInstructions);
+        assert_eq!(conversation.items[1].author, "system");
+        assert_eq!(
+            conversation.items[1].text,
+            "User profile:\n```I write Rust.```\n\nUser's Instructions:\n```Be concise.```"
+        );
+        assert_eq!(conversation.items[1].id.as_deref(), Some("3"));
+        assert_eq!(texts(&conversation)[2..], ["Hi", "Hello."]);
+
+        assert_eq!(texts(&without_system_messages[0]), vec!["Hi", "Hello."]);
+    }
+
+    #[test]
+    fn test_chatgpt_importer() {
+        let message = |id: &str, role: &str, text: &str| {
+            serde_json::json!({
+                "id": id,
+                "author": {"role": role, "name": null, "metadata": {}},
+                "create_time": 1672531200.0,
+                "update_time": null,
+                "content": {"content_type": "text", "parts": [text]},
+                "status": "finished_successfully",
+                "end_turn": null,
+                "weight": 1.0,
+                "metadata": {},
+                "recipient": "all",
+                "channel": null
+            })
+        };
+        let raw = serde_json::json!({
+            "title": "Imported",
+            "create_time": 1672531200.0,
+            "update_time": 1672531200.0,
+            "current_node": "2",
+            "default_model_slug": "gpt-4",
+            "gizmo_id": "g-123",
+            "mapping": {
+                "1": {"id": "1", "message": message("1", "user", "Hello!"), "parent": null, "children": ["2"]},
+                "2": {"id": "2", "message": message("2", "assistant", "Hi!"), "parent": "1", "children": []}
+            }
+        });
+        assert!(ChatGptImporter.detect(&raw));
+
+        let mut switched = raw.clone();
+        switched["mapping"]["2"]["message"]["metadata"] =
+            serde_json::json!({"model_slug": "gpt-4o"});
+
+        let conversations = ChatGptImporter
+            .import(raw, &ImportOptions::default())
+            .unwrap();
+
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(texts(&conversations[0]), vec!["Hello!", "Hi!"]);
+        assert_eq!(conversations[0].model.as_deref(), Some("gpt-4"));
+        assert_eq!(conversations[0].gizmo_id.as_deref(), Some("g-123"));
+
+        let conversations = ChatGptImporter
+            .import(switched, &ImportOptions::default())
+            .unwrap();
+        assert_eq!(conversations[0].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(conversations[0].items[0].model, None);
+        assert_eq!(conversations[0].items[1].model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn test_create_conversation_from_code_interpreter() {
+        let mut code = node("2", Some("1"), &["3"], "assistant", "");
+        let message = code.message.as_mut().unwrap();
+        message.recipient = "python".to_string();
+        message.content = Content {
+            content_type: "code".to_string(),
+            parts: None,
+            text: Some("print(6 * 7)".to_string()),
+            language: Some("unknown".to_string()),
+            thoughts: None,
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let mut output = node("3", Some("2"), &["4"], "tool", "");
+        let message = output.message.as_mut().unwrap();
+        message.author.name = Some("python".to_string());
+        message.content = Content {
+            content_type: "execution_output".to_string(),
+            parts: None,
+            text: Some("42".to_string()),
+            language: None,
+            thoughts: None,
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
+            title: "Analysis".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([
+                (
+                    "1".to_string(),
+                    node("1", None, &["2"], "user", "Compute 6 * 7"),
+                ),
+                ("2".to_string(), code),
+                ("3".to_string(), output),
+                (
+                    "4".to_string(),
+                    node("4", Some("3"), &[], "assistant", "It is 42."),
+                ),
+            ]),
+            current_node: Some("4".to_string()),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        };
+
+        let conversation = create_conversation_from(interaction, &ImportOptions::default());
+
+        assert_eq!(
+            texts(&conversation),
+            vec!["Compute 6 * 7", "print(6 * 7)", "42", "It is 42."]
+        );
+        let code = &conversation.items[1];
+        assert_eq!(code.author, "python");
+        assert_eq!(
+            code.kind,
+            ItemKind::Code {
+                language: Some("python".to_string())
+            }
+        );
+        let output = &conversation.items[2];
+        assert_eq!(output.author, "python");
+        assert_eq!(output.kind, ItemKind::ExecutionOutput);
+        assert_eq!(conversation.items[3].kind, ItemKind::Text);
+    }
+
+    #[test]
+    fn test_create_conversation_from_browser_command() {
+        let mut command = node("2", Some("1"), &["3"], "assistant", "");
+        let message = command.message.as_mut().unwrap();
+        message.recipient = "browser".to_string();
+        message.content = Content {
+            content_type: "code".to_string(),
+            parts: None,
+            text: Some("search(\"rust release date\")".to_string()),
+            language: Some("unknown".to_string()),
+            thoughts: None,
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
+            title: "Browsing".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([
+                (
+                    "1".to_string(),
+                    node("1", None, &["2"], "user", "When was Rust released?"),
+                ),
+                ("2".to_string(), command),
+                (
+                    "3".to_string(),
+                    node("3", Some("2"), &[], "assistant", "In 2015."),
+                ),
+            ]),
+            current_node: Some("3".to_string()),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        };
+
+        let conversation = create_conversation_from(interaction, &ImportOptions::default());
+
+        assert_eq!(
+            texts(&conversation),
+            vec!["When was Rust released?", "In 2015."]
+        );
+        assert!(conversation
+            .items
+            .iter()
+            .all(|item| !matches!(item.kind, ItemKind::Code { .. })));
+    }
+
+    #[test]
+    fn test_create_conversation_from_images() {
+        let mut upload = node("1", None, &["2"], "user", "");
+        upload.message.as_mut().unwrap().content = Content {
+            content_type: "multimodal_text".to_string(),
+            parts: Some(vec![
+                Part::Object(serde_json::json!({
+                    "content_type": "image_asset_pointer",
+                    "asset_pointer": "file-service://file-AbC123",
+                    "width": 640,
+                    "height": 480
+                })),
+                Part::String("What is this?".to_string()),
+            ]),
+            text: None,
+            language: None,
+            thoughts: None,
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let mut generated = node("2", Some("1"), &[], "tool", "");
+        let message = generated.message.as_mut().unwrap();
+        message.author.name = Some("dalle.text2im".to_string());
+        message.content = Content {
+            content_type: "multimodal_text".to_string(),
+            parts: Some(vec![Part::Object(serde_json::json!({
+                "content_type": "image_asset_pointer",
+                "asset_pointer": "sediment://file_00000000abc"
+            }))]),
+            text: None,
+            language: None,
+            thoughts: None,
+            content: None,
+            user_profile: None,
+            user_instructions: None,
+        };
+        let interaction = GPTInteraction {
+            id: None,
+            conversation_id: None,
+            title: "Images".to_string(),
+            create_time: 0.0,
+            update_time: 0.0,
+            mapping: HashMap::from([("1".to_string(), upload), ("2".to_string(), generated)]),
+            current_node: Some("2".to_string()),
+            default_model_slug: None,
+            gizmo_id: None,
+            is_archived: None,
+        };
+
+        let conversation = create_conversation_from(interaction, &ImportOptions::default());
+
+        assert_eq!(conversation.items.len(), 2);
+        assert_eq!(conversation.items[0].text, "What is this?");
+        assert_eq!(
+            conversation.items[0].images,
+            vec![Image::new("file-AbC123".to_string())]
+        );
+        assert_eq!(conversation.items[1].author, "dalle.text2im");
+        assert_eq!(conversation.items[1].text, "");
+        assert_eq!(
+            conversation.items[1].images,
+            vec![Image::new("file_00000000abc".to_string())]
+        );
     }
 }
@@ -0,0 +1,216 @@
+use crate::model::Conversation;
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset, Local, SecondsFormat,
+};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use std::{fmt, str::FromStr};
+
+/// Pattern of the conversation dates used by default, in file names for instance.
+pub const DEFAULT_DATE_PATTERN: &str = "%Y-%m-%d";
+
+/// Pattern of the message times shown in annotations.
+const TIME_PATTERN: &str = "%Y-%m-%d %H:%M";
+
+/// Time zone in which dates and times are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Timezone {
+    /// Coordinated Universal Time.
+    #[default]
+    Utc,
+    /// The time zone of the system running the export.
+    Local,
+    /// A time zone of the IANA database, such as "Europe/Paris".
+    Named(Tz),
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    /// Parses "UTC", "local" (case-insensitive) or an IANA time zone name.
+    fn from_str(name: &str) -> Result<Timezone, String> {
+        if name.eq_ignore_ascii_case("utc") {
+            Ok(Timezone::Utc)
+        } else if name.eq_ignore_ascii_case("local") {
+            Ok(Timezone::Local)
+        } else {
+            name.parse::<Tz>()
+                .map(Timezone::Named)
+                .map_err(|_| format!("unknown time zone '{}', expected an IANA name such as Europe/Paris, UTC or local", name))
+        }
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timezone::Utc => f.write_str("UTC"),
+            Timezone::Local => f.write_str("local"),
+            Timezone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+/// Timestamp of a conversation its date is taken from, selectable from the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum DateField {
+    /// Date the conversation was started
+    Created,
+    /// Date the conversation was last updated
+    #[default]
+    Updated,
+}
+
+/// How dates and times are written in file names, headings, front matter and templates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateFormat {
+    timezone: Timezone,
+    pattern: String,
+    field: DateField,
+}
+
+impl Default for DateFormat {
+    fn default() -> DateFormat {
+        DateFormat {
+            timezone: Timezone::Utc,
+            pattern: DEFAULT_DATE_PATTERN.to_string(),
+            field: DateField::Updated,
+        }
+    }
+}
+
+impl DateFormat {
+    /// Creates a `DateFormat`.
+    ///
+    /// # Arguments
+    ///
+    /// * `timezone` - The `Timezone` in which every date and time is written.
+    ///
+    /// * `pattern` - The strftime pattern of conversation dates, e.g. `%Y-%m-%d`.
+    ///
+    /// * `field` - The `DateField` conversation dates are taken from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if `pattern` is not a valid strftime pattern.
+    pub fn new(timezone: Timezone, pattern: &str, field: DateField) -> Result<DateFormat, String> {
+        if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("invalid date format '{}'", pattern));
+        }
+        Ok(DateFormat {
+            timezone,
+            pattern: pattern.to_string(),
+            field,
+        })
+    }
+
+    /// Returns the date of a conversation, taken from its creation or update time, formatted
+    /// with the pattern of this `DateFormat`.
+    pub fn conversation_date(&self, conversation: &Conversation) -> String {
+        self.format_conversation(conversation, &self.pattern)
+    }
+
+    /// Formats the date of a conversation, taken from its creation or update time, with any
+    /// valid strftime `pattern`.
+    pub fn format_conversation(&self, conversation: &Conversation, pattern: &str) -> String {
+        let time = match self.field {
+            DateField::Created => conversation.create_time,
+            DateField::Updated => conversation.update_time,
+        };
+        self.format(time, pattern)
+    }
+
+    /// Returns an RFC 3339 timestamp, such as `2023-01-01T01:00:00+01:00`, for front matter and
+    /// templates. UTC times end with `Z`.
+    pub fn datetime(&self, epoch_time: f64) -> String {
+        self.to_timezone(epoch_time)
+            .map(|datetime| datetime.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_default()
+    }
+
+    /// Returns the date and time of a message, such as `2023-01-01 10:30`, for annotations.
+    pub fn time(&self, epoch_time: f64) -> String {
+        self.format(epoch_time, TIME_PATTERN)
+    }
+
+    fn format(&self, epoch_time: f64, pattern: &str) -> String {
+        self.to_timezone(epoch_time)
+            .map(|datetime| datetime.format(pattern).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Converts an epoch time to the time zone of this `DateFormat`, or returns `None` if it is
+    /// out of range. Fractional seconds are dropped.
+    fn to_timezone(&self, epoch_time: f64) -> Option<DateTime<FixedOffset>> {
+        let datetime = DateTime::from_timestamp(epoch_time.floor() as i64, 0)?;
+        Some(match self.timezone {
+            Timezone::Utc => datetime.fixed_offset(),
+            Timezone::Local => datetime.with_timezone(&Local).fixed_offset(),
+            Timezone::Named(tz) => datetime.with_timezone(&tz).fixed_offset(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Conversation {
+        let mut conversation = Conversation::new("Late".to_string(), Vec::new(), String::new());
+        conversation.create_time = 1672527600.0; // 2022-12-31T23:00:00Z
+        conversation.update_time = 1672617600.0; // 2023-01-02T00:00:00Z
+        conversation
+    }
+
+    #[test]
+    fn test_timezone_from_str() {
+        assert_eq!("utc".parse::<Timezone>(), Ok(Timezone::Utc));
+        assert_eq!("Local".parse::<Timezone>(), Ok(Timezone::Local));
+        assert_eq!(
+            "Europe/Paris".parse::<Timezone>(),
+            Ok(Timezone::Named(Tz::Europe__Paris))
+        );
+        assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+    }
+
+    #[test]
+    fn test_conversation_date() {
+        let conversation = conversation();
+        assert_eq!(
+            DateFormat::default().conversation_date(&conversation),
+            "2023-01-02"
+        );
+
+        let paris = Timezone::Named(Tz::Europe__Paris);
+        let created = DateFormat::new(paris, "%d.%m.%Y", DateField::Created).unwrap();
+        assert_eq!(created.conversation_date(&conversation), "01.01.2023");
+
+        let new_york = Timezone::Named(Tz::America__New_York);
+        let updated = DateFormat::new(new_york, DEFAULT_DATE_PATTERN, DateField::Updated).unwrap();
+        assert_eq!(updated.conversation_date(&conversation), "2023-01-01");
+    }
+
+    #[test]
+    fn test_datetime_and_time() {
+        assert_eq!(
+            DateFormat::default().datetime(1672531200.5),
+            "2023-01-01T00:00:00Z"
+        );
+        assert_eq!(DateFormat::default().time(1672531200.0), "2023-01-01 00:00");
+
+        let paris = DateFormat::new(
+            Timezone::Named(Tz::Europe__Paris),
+            DEFAULT_DATE_PATTERN,
+            DateField::Updated,
+        )
+        .unwrap();
+        assert_eq!(paris.datetime(1672531200.0), "2023-01-01T01:00:00+01:00");
+        assert_eq!(paris.time(1688169600.0), "2023-07-01 02:00");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(DateFormat::new(Timezone::Utc, "%Y-%Q", DateField::Updated).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// An error of an export run.
+///
+/// Errors reading the input file or the options stop the run. Errors affecting a single
+/// conversation or file are collected instead, so that the rest of the export is still written,
+/// and reported at the end of the run.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An option given on the command line is invalid, such as a malformed layout or template.
+    Options(String),
+    /// A file could not be opened or read.
+    Read {
+        /// Path of the file.
+        path: PathBuf,
+        /// Error of the read.
+        source: io::Error,
+    },
+    /// A file is not valid JSON, or not a list of conversations.
+    Parse {
+        /// Path of the file.
+        path: PathBuf,
+        /// Line of the error, starting at 1, or 0 if it is not located in the file.
+        line: usize,
+        /// Column of the error, starting at 1, or 0 if it is not located in the file.
+        column: usize,
+        /// Description of the error, without its location.
+        message: String,
+    },
+    /// A conversation of the export could not be converted.
+    Convert {
+        /// Path of the export.
+        path: PathBuf,
+        /// Name of the `Importer` that failed, e.g. "chatgpt".
+        importer: &'static str,
+        /// Position of the conversation in the export, starting at 0.
+        index: usize,
+        /// Description of the error.
+        message: String,
+    },
+    /// A file or folder of the output could not be written or removed.
+    Write {
+        /// Path of the file or folder.
+        path: PathBuf,
+        /// Error of the write.
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Creates an `Error::Read` for the file at `path`.
+    pub fn read(path: &Path, source: io::Error) -> Error {
+        Error::Read {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Creates an `Error::Write` for the file or folder at `path`.
+    pub fn write(path: &Path, source: io::Error) -> Error {
+        Error::Write {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Creates an `Error::Parse` for the file at `path`, locating it with the line and column
+    /// of the `serde_json::Error`.
+    pub fn parse(path: &Path, error: serde_json::Error) -> Error {
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        // The location is kept in the fields rather than repeated in the message.
+        let location = format!(" at line {} column {}", line, column);
+        let message = message
+            .strip_suffix(&location)
+            .map(str::to_string)
+            .unwrap_or(message);
+        Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    }
+
+    /// Name of the kind of error, used to count errors in the report of a run.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Options(_) => "options",
+            Error::Read { .. } => "read",
+            Error::Parse { .. } => "parse",
+            Error::Convert { .. } => "conversion",
+            Error::Write { .. } => "write",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Options(message) => f.write_str(message),
+            Error::Read { path, source } => {
+                write!(f, "Failed to read '{}': {}", path.display(), source)
+            }
+            Error::Parse {
+                path,
+                line,
+                column,
+                message,
+            } if *line > 0 => write!(
+                f,
+                "Failed to parse JSON in file '{}' at line {}, column {}: {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            Error::Parse { path, message, .. } => {
+                write!(
+                    f,
+                    "Failed to parse JSON in file '{}': {}",
+                    path.display(),
+                    message
+                )
+            }
+            Error::Convert {
+                path,
+                importer,
+                index,
+                message,
+            } => write!(
+                f,
+                "Failed to parse {} conversation #{} in file '{}': {}",
+                importer,
+                index + 1,
+                path.display(),
+                message
+            ),
+            Error::Write { path, source } => {
+                write!(f, "Failed to write '{}': {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read { source, .. } | Error::Write { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that did not stop a run, collected from the threads writing the export.
+#[derive(Debug, Default)]
+pub(crate) struct Errors(Mutex<Vec<Error>>);
+
+impl Errors {
+    /// Logs an error as soon as it occurs, and keeps it for the report of the run.
+    pub fn push(&self, error: Error) {
+        log::error!("{}", error);
+        self.0.lock().unwrap().push(error);
+    }
+
+    /// Keeps errors that were already logged, such as the errors returned by
+    /// `conversation_writer::write`.
+    pub fn extend(&self, errors: Vec<Error>) {
+        self.0.lock().unwrap().extend(errors);
+    }
+
+    /// Returns `true` if no error occurred so far.
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+
+    /// Returns the collected errors, in the order they occurred.
+    pub fn into_inner(self) -> Vec<Error> {
+        self.0.into_inner().unwrap()
+    }
+}
+
+/// Summarizes errors by kind, e.g. "1 conversion error, 2 write errors".
+///
+/// # Returns
+///
+/// The summary, or `None` if there is no error.
+pub fn summary(errors: &[Error]) -> Option<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for error in errors {
+        match counts.iter_mut().find(|(kind, _)| *kind == error.kind()) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((error.kind(), 1)),
+        }
+    }
+    if counts.is_empty() {
+        return None;
+    }
+    counts.sort();
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| {
+            let plural = if *count > 1 { "s" } else { "" };
+            format!("{} {} error{}", count, kind, plural)
+        })
+        .collect();
+    Some(counts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let error = serde_json::from_str::<serde_json::Value>("[\n  {,\n]").unwrap_err();
+        let error = Error::parse(Path::new("conversations.json"), error);
+        assert!(matches!(
+            error,
+            Error::Parse {
+                line: 2,
+                column: 4,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Failed to parse JSON in file 'conversations.json' at line 2, column 4: key must be a string"
+        );
+    }
+
+    #[test]
+    fn test_errors_summary() {
+        let errors = Errors::default();
+        assert_eq!(summary(&errors.into_inner()), None);
+
+        let errors = Errors::default();
+        let write = || Error::write(Path::new("out/a.md"), io::ErrorKind::Other.into());
+        errors.push(write());
+        errors.push(Error::Convert {
+            path: PathBuf::from("conversations.json"),
+            importer: "chatgpt",
+            index: 0,
+            message: "missing field `title`".to_string(),
+        });
+        errors.push(write());
+        assert_eq!(
+            summary(&errors.into_inner()).as_deref(),
+            Some("1 conversion error, 2 write errors")
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{
+    conversation_writer::{self, FileNames},
+    error::{Error, Errors},
+    export_source::{ExportSource, ASSETS_FOLDER},
+    exporter::{ExportOptions, Exporter, OutputFormat},
+    filter::Filter,
+    importer::{self, ImportOptions, Importer, InputFormat},
+    layout::Layout,
+    manifest::{Changes, Manifest, MANIFEST_FILE},
+    model::Conversation,
+};
+use log::{debug, info};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::{
+    io::Read,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// Number of parsed conversations waiting to be converted and written. Bounds the memory used
+/// by the pipeline regardless of the size of the export.
+const PIPELINE_CAPACITY: usize = 64;
+
+/// Settings of an export run: the data export to read, the folder to write it to, and the
+/// options of each step, as given on the command line.
+///
+/// `Config::new` leaves every option to its default, the fields being set afterwards.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Config {
+    /// Path to the `conversations.json` file, or to the ZIP archive of the data export.
+    pub input: PathBuf,
+    /// Folder the files are written to, created if needed.
+    pub output_folder: PathBuf,
+    /// Source of the export, detected from its first conversation by default.
+    pub source: InputFormat,
+    /// Formats of the written files, Markdown by default. A format listed twice is written once.
+    pub formats: Vec<OutputFormat>,
+    /// Options of the conversion of the conversations.
+    pub import_options: ImportOptions,
+    /// Options of the rendering of the files. Their `date_format` also gives the date of each
+    /// conversation, used in file names.
+    pub export_options: ExportOptions,
+    /// Subfolders the files are written to.
+    pub layout: Layout,
+    /// Selection of the conversations to export.
+    pub filter: Filter,
+    /// Remove the files of the conversations that are no longer exported, unless the run has
+    /// errors.
+    pub prune: bool,
+}
+
+impl Config {
+    /// Creates the settings of an export of `input` to `output_folder`, with the default
+    /// options.
+    pub fn new(input: impl Into<PathBuf>, output_folder: impl Into<PathBuf>) -> Config {
+        Config {
+            input: input.into(),
+            output_folder: output_folder.into(),
+            source: InputFormat::default(),
+            formats: vec![OutputFormat::default()],
+            import_options: ImportOptions::default(),
+            export_options: ExportOptions::default(),
+            layout: Layout::default(),
+            filter: Filter::default(),
+            prune: false,
+        }
+    }
+}
+
+/// Outcome of an export run.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Report {
+    /// Files created, updated, removed and disambiguated in the output folder.
+    pub changes: Changes,
+    /// Number of conversations left out by the `Filter`.
+    pub filtered: usize,
+    /// Whether pruning was requested but skipped because of the errors of the run, so that the
+    /// files of the conversations that failed are not removed.
+    pub prune_skipped: bool,
+    /// Errors that did not stop the run, such as conversations that could not be converted or
+    /// files that could not be written, in the order they occurred. They are logged as they
+    /// occur.
+    pub errors: Vec<Error>,
+    /// Error that stopped the reading of the export partway, such as invalid JSON. The
+    /// conversations read before it are written all the same, along with their media and the
+    /// manifest.
+    pub stopped: Option<Error>,
+}
+
+/// Exports the conversations of a data export to an output folder, as the command line does.
+///
+/// The export is read twice. The conversations are parsed one at a time, then converted and
+/// filtered in parallel as they arrive, first to claim the names of their files (see
+/// `FileNames`), then to be written, so that the memory used does not depend on the size of the
+/// export. The media they link to are then copied to the `assets` folder, the files of the
+/// conversations that are no longer exported are removed if `Config::prune` is set, and the
+/// manifest of the output folder is saved.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` of the run.
+///
+/// # Returns
+///
+/// The `Report` of the run, listing the changes made to the output folder and the errors that
+/// did not stop it.
+///
+/// # Errors
+///
+/// Returns an `Error` if the input file, or the manifest of the output folder, cannot be read,
+/// in which case nothing was written, or if the manifest cannot be saved.
+pub fn export(config: &Config) -> Result<Report, Error> {
+    let input = &config.input;
+    let mut source = ExportSource::open(input).map_err(|e| Error::read(input, e))?;
+    let media = source.media_index().map_err(|e| Error::read(input, e))?;
+
+    let mut exporters: Vec<Box<dyn Exporter>> = Vec::new();
+    for (index, format) in config.formats.iter().enumerate() {
+        if !config.formats[..index].contains(format) {
+            exporters.push(format.exporter(&config.export_options));
+        }
+    }
+    let output_folder = &config.output_folder;
+    let manifest_path = output_folder.join(MANIFEST_FILE);
+    let mut manifest = Manifest::load(output_folder).map_err(|e| Error::read(&manifest_path, e))?;
+    let errors = Errors::default();
+    let filtered = AtomicUsize::new(0);
+
+    // The errors of the first reading are reported by the second one, which meets them again.
+    let names = FileNames::new(config.layout.clone());
+    let reader = source
+        .conversations_reader()
+        .map_err(|e| Error::read(input, e))?;
+    let (_, ()) = stream(config, reader, "to name their files", |raw_conversations| {
+        raw_conversations
+            .par_bridge()
+            .flat_map_iter(|raw_conversation| convert(config, raw_conversation).unwrap_or_default())
+            .filter(|conversation| config.filter.matches(conversation))
+            .for_each(|conversation| names.claim(&conversation));
+    });
+
+    let reader = source
+        .conversations_reader()
+        .map_err(|e| Error::read(input, e))?;
+    let (parsed, (mut changes, write_errors)) =
+        stream(config, reader, "to write them", |raw_conversations| {
+            let conversations = raw_conversations
+                .par_bridge()
+                .flat_map_iter(|raw_conversation| {
+                    let mut conversations =
+                        convert(config, raw_conversation).unwrap_or_else(|err| {
+                            errors.push(err);
+                            Vec::new()
+                        });
+                    let count = conversations.len();
+                    conversations.retain(|conversation| {
+                        let matches = config.filter.matches(conversation);
+                        if !matches {
+                            debug!(
+                                "Left out conversation '{}' by the filters",
+                                conversation.title
+                            );
+                        }
+                        matches
+                    });
+                    filtered.fetch_add(count - conversations.len(), Ordering::Relaxed);
+                    for conversation in &mut conversations {
+                        media.link_images(conversation);
+                    }
+                    conversations
+                });
+            conversation_writer::write(
+                conversations,
+                output_folder,
+                &exporters,
+                names,
+                &mut manifest,
+            )
+        });
+    errors.extend(write_errors);
+    // The conversations read before a parse error are written, so their media and manifest
+    // entries are saved as well.
+    let stopped = match parsed {
+        Ok(parsed) => {
+            info!("Read {} conversations", parsed);
+            None
+        }
+        Err(err) => Some(Error::parse(input, err)),
+    };
+
+    let assets_folder = output_folder.join(ASSETS_FOLDER);
+    match source.copy_media(&media, &assets_folder) {
+        Ok(0) => {}
+        Ok(copied) => info!(
+            "Copied {} media files to '{}'",
+            copied,
+            assets_folder.display()
+        ),
+        Err(err) => errors.push(Error::write(&assets_folder, err)),
+    }
+
+    // A conversation that could not be read, converted or written would lose its files.
+    let prune_skipped = config.prune && (stopped.is_some() || !errors.is_empty());
+    if config.prune && !prune_skipped {
+        match manifest.prune(output_folder) {
+            Ok(removed) => changes.removed = removed,
+            Err(err) => errors.push(Error::write(output_folder, err)),
+        }
+    }
+    manifest
+        .save(output_folder)
+        .map_err(|e| Error::write(&manifest_path, e))?;
+    Ok(Report {
+        changes,
+        filtered: filtered.into_inner(),
+        prune_skipped,
+        errors: errors.into_inner(),
+        stopped,
+    })
+}
+
+/// A conversation of the export as parsed, with its position in the export and the `Importer`
+/// converting it.
+type RawConversation = (usize, &'static dyn Importer, Value);
+
+/// Parses the conversations of the export one at a time on this thread, and hands them to
+/// `consume` on another thread as they are parsed.
+///
+/// # Arguments
+///
+/// * `config` - The `Config` of the run, giving the source of the export.
+///
+/// * `reader` - The JSON document of the export.
+///
+/// * `purpose` - Why the export is read, for the log.
+///
+/// * `consume` - Receives the parsed conversations, in the order of the export.
+///
+/// # Returns
+///
+/// The number of conversations parsed, or the error that stopped the parsing, and the result of
+/// `consume`.
+fn stream<R, F>(
+    config: &Config,
+    reader: impl Read,
+    purpose: &str,
+    consume: F,
+) -> (serde_json::Result<usize>, R)
+where
+    R: Send,
+    F: FnOnce(mpsc::IntoIter<RawConversation>) -> R + Send,
+{
+    let (sender, receiver) = mpsc::sync_channel::<RawConversation>(PIPELINE_CAPACITY);
+    thread::scope(|scope| {
+        let consumer = scope.spawn(|| consume(receiver.into_iter()));
+        let mut importer = None;
+        let parsed = importer::for_each_conversation(reader, move |index, raw_conversation| {
+            let importer = match importer {
+                Some(importer) => importer,
+                None => {
+                    let detected = config
+                        .source
+                        .importer(&raw_conversation)
+                        .ok_or("unrecognized conversation format, use --source to specify it")?;
+                    info!(
+                        "Reading {} conversations from '{}' {}",
+                        detected.name(),
+                        config.input.display(),
+                        purpose
+                    );
+                    *importer.insert(detected)
+                }
+            };
+            sender
+                .send((index, importer, raw_conversation))
+                .map_err(|e| e.to_string())
+        });
+        (parsed, consumer.join().expect("the writer thread panicked"))
+    })
+}
+
+/// Converts a conversation of the export into the `Conversation`s to write, dated by the
+/// `DateFormat` of the export options and tagged with the name of their importer.
+///
+/// # Errors
+///
+/// Returns an `Error::Convert` if the conversation cannot be converted.
+fn convert(
+    config: &Config,
+    (index, importer, raw_conversation): RawConversation,
+) -> Result<Vec<Conversation>, Error> {
+    let mut conversations = importer
+        .import(raw_conversation, &config.import_options)
+        .map_err(|e| Error::Convert {
+            path: config.input.clone(),
+            importer: importer.name(),
+            index,
+            message: e.to_string(),
+        })?;
+    for conversation in &mut conversations {
+        conversation.source = Some(importer.name());
+        conversation.date = config
+            .export_options
+            .date_format
+            .conversation_date(conversation);
+    }
+    Ok(conversations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn conversation(id: &str, title: &str) -> Value {
+        json!({
+            "uuid": id,
+            "name": title,
+            "created_at": "2024-03-01T10:00:00Z",
+            "updated_at": "2024-03-01T10:00:05Z",
+            "chat_messages": [{
+                "uuid": format!("{}-m1", id),
+                "text": "Hello!",
+                "sender": "human",
+                "created_at": "2024-03-01T10:00:00Z",
+                "updated_at": "2024-03-01T10:00:00Z"
+            }]
+        })
+    }
+
+    #[test]
+    fn test_export() {
+        let folder = PathBuf::from("./test_export");
+        let input = folder.join("conversations.json");
+        let output_folder = folder.join("output");
+        fs::create_dir_all(&folder).unwrap();
+        let export_of = |conversations: Vec<Value>| serde_json::to_string(&conversations).unwrap();
+
+        fs::write(
+            &input,
+            export_of(vec![conversation("c1", "One"), conversation("c2", "Two")]),
+        )
+        .unwrap();
+        let mut config = Config::new(&input, &output_folder);
+        config.filter.excluded_ids = vec!["c2".to_string()];
+        let report = export(&config).unwrap();
+        assert_eq!(report.changes.created, vec!["2024-03-01-One.md"]);
+        assert_eq!(report.filtered, 1);
+        assert!(report.errors.is_empty());
+        assert!(report.stopped.is_none());
+
+        // An export breaking off after its first conversation is written up to there, but
+        // nothing is pruned.
+        let three = export_of(vec![conversation("c3", "Three")]);
+        fs::write(&input, format!("{},]", three.trim_end_matches(']'))).unwrap();
+        config.prune = true;
+        let report = export(&config).unwrap();
+        assert_eq!(report.changes.created, vec!["2024-03-01-Three.md"]);
+        assert!(report.prune_skipped);
+        assert!(matches!(report.stopped, Some(Error::Parse { .. })));
+        assert!(output_folder.join("2024-03-01-One.md").exists());
+        let manifest = Manifest::load(&output_folder).unwrap();
+        assert!(manifest.get("2024-03-01-Three.md").is_some());
+
+        // A conversation created later with the same title is told apart, even when it comes
+        // first in the export.
+        let mut later = conversation("c5", "One");
+        later["created_at"] = json!("2024-03-01T11:00:00Z");
+        fs::write(&input, export_of(vec![later, conversation("c1", "One")])).unwrap();
+        let report = export(&config).unwrap();
+        assert_eq!(report.changes.created, vec!["2024-03-01-One-c5.md"]);
+        assert_eq!(report.changes.disambiguated, vec!["2024-03-01-One-c5.md"]);
+        assert_eq!(report.changes.unchanged, 1);
+
+        // Clean up
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
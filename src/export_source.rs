@@ -0,0 +1,398 @@
+use crate::model::Conversation;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use zip::ZipArchive;
+
+/// Name of the file holding the conversations in a data export.
+const CONVERSATIONS_FILE: &str = "conversations.json";
+
+/// Folder of the output receiving the media files linked from the conversations.
+pub const ASSETS_FOLDER: &str = "assets";
+
+/// The input of herodote: either a JSON file, or the ZIP archive of a data export.
+///
+/// Besides the conversations, a data export holds the media attached to them, such as uploaded
+/// images and generated pictures. Their file names start with `file-` or `file_` followed by the
+/// id of the file, possibly in subfolders such as `dalle-generations/`. For a ZIP
+/// archive, media are read from the archive itself; for a JSON file, they are looked up in the
+/// folder containing it, which covers exports that were unzipped by hand.
+pub enum ExportSource {
+    /// A `conversations.json` file, with the media of the export in its folder.
+    Json(PathBuf),
+    /// A ZIP archive of the export.
+    Zip(ZipArchive<File>),
+}
+
+impl ExportSource {
+    /// Opens an export, recognizing ZIP archives by their `.zip` extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be opened or is not a valid ZIP file.
+    pub fn open(path: &Path) -> io::Result<ExportSource> {
+        let is_zip = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+        if !is_zip {
+            return Ok(ExportSource::Json(path.to_path_buf()));
+        }
+        let archive = ZipArchive::new(File::open(path)?).map_err(io::Error::other)?;
+        Ok(ExportSource::Zip(archive))
+    }
+
+    /// Opens a buffered reader over the JSON document holding the conversations.
+    ///
+    /// In a ZIP archive, this is the `conversations.json` entry closest to the root of the
+    /// archive, so that exports wrapped in a top-level folder are supported as well. The entry
+    /// is decompressed while it is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, or if the archive has no
+    /// `conversations.json`.
+    pub fn conversations_reader(&mut self) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            ExportSource::Json(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+            ExportSource::Zip(archive) => {
+                let name = archive
+                    .file_names()
+                    .filter(|name| name.rsplit('/').next() == Some(CONVERSATIONS_FILE))
+                    .min_by_key(|name| (name.matches('/').count(), name.to_string()))
+                    .map(str::to_string)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("no {} in archive", CONVERSATIONS_FILE),
+                        )
+                    })?;
+                let entry = archive.by_name(&name).map_err(io::Error::other)?;
+                Ok(Box::new(BufReader::new(entry)))
+            }
+        }
+    }
+
+    /// Lists the media files of the export, without reading them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the folder holding a JSON file cannot be listed.
+    pub fn media_index(&mut self) -> io::Result<MediaIndex> {
+        let mut locations = Vec::new();
+        match self {
+            ExportSource::Json(path) => {
+                for file in media_files_in(export_folder(path))? {
+                    if let Some(location) = file.to_str() {
+                        locations.push(location.to_string());
+                    }
+                }
+            }
+            ExportSource::Zip(archive) => {
+                locations.extend(
+                    archive
+                        .file_names()
+                        .filter(|name| !name.ends_with('/'))
+                        .filter(|name| name.rsplit('/').next().is_some_and(is_media))
+                        .map(str::to_string),
+                );
+                locations.sort();
+            }
+        }
+
+        let mut files = HashMap::new();
+        for location in locations {
+            let name = location
+                .rsplit(['/', std::path::MAIN_SEPARATOR])
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            if let Some(file_id) = file_id(&name) {
+                files
+                    .entry(file_id.to_string())
+                    .or_insert(MediaFile { name, location });
+            }
+        }
+        Ok(MediaIndex {
+            files,
+            used: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// Copies the media files linked from the conversations into `folder`, flattening
+    /// subfolders.
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - The `MediaIndex` of this export, after `MediaIndex::link_images` was called
+    ///   on every conversation.
+    ///
+    /// * `folder` - The folder receiving the copies, usually the `ASSETS_FOLDER` of the output.
+    ///
+    /// # Returns
+    ///
+    /// The number of media files copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the folder cannot be created, or if a media file cannot be read or
+    /// written. Files copied before the error are kept.
+    pub fn copy_media(&mut self, media: &MediaIndex, folder: &Path) -> io::Result<usize> {
+        let used = media.used.lock().unwrap_or_else(|e| e.into_inner());
+        for file_id in used.iter() {
+            let file = &media.files[file_id];
+            fs::create_dir_all(folder)?;
+            let destination = folder.join(&file.name);
+            match self {
+                ExportSource::Json(_) => {
+                    fs::copy(&file.location, destination)?;
+                }
+                ExportSource::Zip(archive) => {
+                    let mut entry = archive.by_name(&file.location).map_err(io::Error::other)?;
+                    io::copy(&mut entry, &mut File::create(destination)?)?;
+                }
+            }
+        }
+        Ok(used.len())
+    }
+}
+
+/// The media files of an export, indexed by the id of their file (e.g. `file-AbC123`).
+///
+/// The index also records which files are linked from the conversations, so that only those
+/// are copied to the output folder.
+pub struct MediaIndex {
+    files: HashMap<String, MediaFile>,
+    used: Mutex<BTreeSet<String>>,
+}
+
+struct MediaFile {
+    /// Name of the file, without folders.
+    name: String,
+    /// Path of the file in the archive, or on the file system for a JSON export.
+    location: String,
+}
+
+impl MediaIndex {
+    /// Resolves the images of a conversation to the media files of the export.
+    ///
+    /// Each `Image` whose file is found gets the path of its copy in the `ASSETS_FOLDER`, and
+    /// the file is marked to be copied by `ExportSource::copy_media`. Images whose file is
+    /// missing from the export keep a `None` path.
+    pub fn link_images(&self, conversation: &mut Conversation) {
+        for image in conversation
+            .items
+            .iter_mut()
+            .flat_map(|item| item.images.iter_mut())
+        {
+            let Some(file) = self.files.get(&image.file_id) else {
+                continue;
+            };
+            image.path = Some(format!("{}/{}", ASSETS_FOLDER, file.name));
+            self.used
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(image.file_id.clone());
+        }
+    }
+}
+
+/// Returns the folder holding a JSON export, in which its media are looked up.
+fn export_folder(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Returns the id of a media file from its name, e.g. `file-AbC123` for `file-AbC123-photo.png`.
+fn file_id(name: &str) -> Option<&str> {
+    if !is_media(name) {
+        return None;
+    }
+    let end = name[5..]
+        .find(['-', '.'])
+        .map_or(name.len(), |index| index + 5);
+    Some(&name[..end])
+}
+
+/// Returns whether a file name is the one of a media file of a data export.
+fn is_media(name: &str) -> bool {
+    (name.starts_with("file-") || name.starts_with("file_")) && !name.ends_with(".json")
+}
+
+/// Lists the media files found in `folder` and its direct subfolders, which is where a data
+/// export stores them.
+fn media_files_in(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut subfolders = Vec::new();
+    list_media_files(folder, &mut files, Some(&mut subfolders))?;
+    for subfolder in subfolders {
+        list_media_files(&subfolder, &mut files, None)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn list_media_files(
+    folder: &Path,
+    files: &mut Vec<PathBuf>,
+    mut subfolders: Option<&mut Vec<PathBuf>>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(subfolders) = subfolders.as_mut() {
+                subfolders.push(path);
+            }
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(is_media)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ConversationItem, Image};
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn conversation_with_images(file_ids: &[&str]) -> Conversation {
+        let mut item = ConversationItem::new(String::new(), "user".to_string(), 0.0);
+        item.images = file_ids
+            .iter()
+            .map(|file_id| Image::new(file_id.to_string()))
+            .collect();
+        Conversation::new("Images".to_string(), vec![item], "2023-01-01".to_string())
+    }
+
+    fn read_conversations(source: &mut ExportSource) -> String {
+        let mut content = String::new();
+        source
+            .conversations_reader()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_zip_export_source() {
+        let folder = PathBuf::from("./test_export_source");
+        fs::create_dir_all(&folder).unwrap();
+        let zip_path = folder.join("export.zip");
+        let mut writer = ZipWriter::new(File::create(&zip_path).unwrap());
+        for (name, content) in [
+            ("chat.html", "<html></html>"),
+            ("export/conversations.json", "[]"),
+            ("export/user.json", "{}"),
+            ("export/file-abc123-photo.png", "png"),
+            ("export/dalle-generations/file-def456-picture.webp", "webp"),
+            ("export/nested/conversations.json", "[{}]"),
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut source = ExportSource::open(&zip_path).unwrap();
+        assert_eq!(read_conversations(&mut source), "[]");
+
+        let media = source.media_index().unwrap();
+        let mut conversation = conversation_with_images(&["file-abc123", "file-missing"]);
+        media.link_images(&mut conversation);
+        let images = &conversation.items[0].images;
+        assert_eq!(
+            images[0].path.as_deref(),
+            Some("assets/file-abc123-photo.png")
+        );
+        assert_eq!(images[1].path, None);
+
+        let assets = folder.join("assets");
+        assert_eq!(source.copy_media(&media, &assets).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(assets.join("file-abc123-photo.png")).unwrap(),
+            "png"
+        );
+        assert!(!assets.join("file-def456-picture.webp").exists());
+
+        // Clean up
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn test_file_id() {
+        assert_eq!(file_id("file-AbC123-photo.png"), Some("file-AbC123"));
+        assert_eq!(file_id("file-AbC123.webp"), Some("file-AbC123"));
+        assert_eq!(
+            file_id("file_00000000abc-3f1e2d.png"),
+            Some("file_00000000abc")
+        );
+        assert_eq!(file_id("file-AbC123"), Some("file-AbC123"));
+        assert_eq!(file_id("photo.png"), None);
+    }
+
+    #[test]
+    fn test_json_export_source() {
+        let folder = PathBuf::from("./test_export_source_json");
+        fs::create_dir_all(folder.join("dalle-generations/deeper")).unwrap();
+        fs::write(folder.join("conversations.json"), "[]").unwrap();
+        fs::write(folder.join("file-abc123-photo.png"), "png").unwrap();
+        fs::write(folder.join("dalle-generations/file-def456.webp"), "webp").unwrap();
+        fs::write(
+            folder.join("dalle-generations/deeper/file-ghi789.png"),
+            "png",
+        )
+        .unwrap();
+        fs::write(folder.join("notes.txt"), "notes").unwrap();
+
+        let mut source = ExportSource::open(&folder.join("conversations.json")).unwrap();
+        assert_eq!(read_conversations(&mut source), "[]");
+
+        let media = source.media_index().unwrap();
+        let mut conversation =
+            conversation_with_images(&["file-abc123", "file-def456", "file-ghi789"]);
+        media.link_images(&mut conversation);
+        let images = &conversation.items[0].images;
+        assert_eq!(images[1].path.as_deref(), Some("assets/file-def456.webp"));
+        assert_eq!(images[2].path, None);
+
+        let assets = PathBuf::from("./test_export_source_json_assets");
+        assert_eq!(source.copy_media(&media, &assets).unwrap(), 2);
+        assert!(assets.join("file-abc123-photo.png").exists());
+        assert!(assets.join("file-def456.webp").exists());
+
+        // Clean up
+        fs::remove_dir_all(folder).unwrap();
+        fs::remove_dir_all(assets).unwrap();
+    }
+
+    #[test]
+    fn test_zip_export_source_without_conversations() {
+        let folder = PathBuf::from("./test_export_source_empty");
+        fs::create_dir_all(&folder).unwrap();
+        let zip_path = folder.join("export.zip");
+        let mut writer = ZipWriter::new(File::create(&zip_path).unwrap());
+        writer
+            .start_file("user.json", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let mut source = ExportSource::open(&zip_path).unwrap();
+        let err = source.conversations_reader().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Clean up
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    date_format::DateFormat, front_matter::FrontMatter, html_renderer::HtmlExporter,
+    markdown_renderer::MarkdownExporter, model::Conversation, template::Template,
+};
+use clap::ValueEnum;
+use std::sync::Arc;
+
+/// An output format for conversations.
+///
+/// `conversation_writer::write` takes care of naming, parallelism and file system access, and
+/// relies on an `Exporter` for everything specific to a format: the bytes of each file, their
+/// extension, and an optional index file aggregating the whole export. Implementing this trait
+/// is all that is needed to add a new format.
+pub trait Exporter: Send + Sync {
+    /// Returns the extension of the files produced by this exporter, without the leading dot.
+    fn extension(&self) -> &str;
+
+    /// Renders a single conversation to the content of its file.
+    fn render(&self, conversation: &Conversation) -> Vec<u8>;
+
+    /// Renders an index of the exported conversations, written as `index.<extension>` next to
+    /// them. Returns `None`, the default, when the format has no index.
+    fn render_index(&self, _entries: &[IndexEntry]) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// A conversation written by an `Exporter`, as listed in its index.
+pub struct IndexEntry {
+    /// Title of the conversation.
+    pub title: String,
+    /// Date of the conversation, as written in file names.
+    pub date: String,
+    /// Time of the last update of the conversation, in seconds since the Unix epoch.
+    pub update_time: f64,
+    /// Name of the exported file, relative to the output folder.
+    pub filename: String,
+}
+
+/// Options shared by every `Exporter`. Options a format has no use for are ignored.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ExportOptions {
+    /// Syntax of the metadata block heading Markdown files, or `None` for no block.
+    pub front_matter: Option<FrontMatter>,
+    /// User-defined template replacing the built-in layout of Markdown files.
+    pub template: Option<Arc<Template>>,
+    /// Time zone and patterns of the dates and times written in the files.
+    pub date_format: DateFormat,
+    /// Annotate each message with its time and, for answers, the model that wrote it.
+    pub annotate: bool,
+    /// Mark the conversation and each message with an anchor named after its id in the export,
+    /// so that links can point to a given answer.
+    pub anchors: bool,
+}
+
+/// Output formats built into herodote, selectable from the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// One Markdown file per conversation
+    #[default]
+    Markdown,
+    /// One self-contained HTML file per conversation, plus an index.html
+    Html,
+}
+
+impl OutputFormat {
+    /// Returns the `Exporter` implementing this format, configured with `options`.
+    pub fn exporter(self, options: &ExportOptions) -> Box<dyn Exporter> {
+        match self {
+            OutputFormat::Markdown => Box::new(MarkdownExporter {
+                options: options.clone(),
+            }),
+            OutputFormat::Html => Box::new(HtmlExporter {
+                options: options.clone(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{date_format::DateFormat, model::Conversation};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use regex::Regex;
+
+/// Which conversations to export depending on whether they were archived, selectable from the
+/// command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Archived {
+    /// Export archived conversations along with the others
+    #[default]
+    Include,
+    /// Leave archived conversations out
+    Exclude,
+    /// Export archived conversations only
+    Only,
+}
+
+/// Criteria a conversation must meet to be exported.
+///
+/// Every criterion is optional: the default `Filter` lets every conversation through. When
+/// several criteria are set, a conversation must meet all of them. Lists such as `models` or
+/// `ids` are alternatives: a conversation meets the criterion if it matches any entry.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Filter {
+    /// First date exported, inclusive, compared with the date of the conversation.
+    pub since: Option<NaiveDate>,
+    /// Last date exported, inclusive, compared with the date of the conversation.
+    pub until: Option<NaiveDate>,
+    /// `DateFormat` giving the time zone and timestamp of the date of the conversations.
+    pub date_format: DateFormat,
+    /// Regular expression the title must match.
+    pub title: Option<Regex>,
+    /// Keywords one of the messages must contain, ignoring case.
+    pub keywords: Vec<String>,
+    /// Minimum number of questions asked by the user.
+    pub min_turns: usize,
+    /// Slugs of models, one of which must have written the conversation or one of its answers.
+    pub models: Vec<String>,
+    /// Identifiers of the custom GPTs or projects the conversation must belong to.
+    pub gizmos: Vec<String>,
+    /// Whether archived conversations are let through, left out or the only ones let through.
+    pub archived: Archived,
+    /// Identifiers of the only conversations exported, when not empty.
+    pub ids: Vec<String>,
+    /// Identifiers of conversations left out.
+    pub excluded_ids: Vec<String>,
+}
+
+impl Filter {
+    /// Tells whether a conversation meets every criterion of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `conversation` - The `Conversation` to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the conversation should be exported.
+    pub fn matches(&self, conversation: &Conversation) -> bool {
+        self.matches_date(conversation)
+            && self.matches_id(conversation)
+            && match self.archived {
+                Archived::Include => true,
+                Archived::Exclude => !conversation.archived,
+                Archived::Only => conversation.archived,
+            }
+            && (self.gizmos.is_empty()
+                || conversation
+                    .gizmo_id
+                    .as_ref()
+                    .is_some_and(|gizmo_id| self.gizmos.contains(gizmo_id)))
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|title| title.is_match(&conversation.title))
+            && self.matches_models(conversation)
+            && conversation
+                .items
+                .iter()
+                .filter(|item| item.author == "user")
+                .count()
+                >= self.min_turns
+            && self.matches_keywords(conversation)
+    }
+
+    fn matches_date(&self, conversation: &Conversation) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let date = self
+            .date_format
+            .format_conversation(conversation, "%Y-%m-%d");
+        let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") else {
+            // The date of the conversation is unknown.
+            return false;
+        };
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+
+    fn matches_id(&self, conversation: &Conversation) -> bool {
+        match &conversation.id {
+            Some(id) => {
+                (self.ids.is_empty() || self.ids.contains(id)) && !self.excluded_ids.contains(id)
+            }
+            None => self.ids.is_empty(),
+        }
+    }
+
+    fn matches_models(&self, conversation: &Conversation) -> bool {
+        self.models.is_empty()
+            || conversation
+                .model
+                .iter()
+                .chain(
+                    conversation
+                        .items
+                        .iter()
+                        .filter_map(|item| item.model.as_ref()),
+                )
+                .any(|model| self.models.contains(model))
+    }
+
+    fn matches_keywords(&self, conversation: &Conversation) -> bool {
+        self.keywords.is_empty()
+            || conversation.items.iter().any(|item| {
+                let text = item.text.to_lowercase();
+                self.keywords
+                    .iter()
+                    .any(|keyword| text.contains(&keyword.to_lowercase()))
+            })
+    }
+}
+
+/// Parses a date given on the command line, such as `2024-03-31`.
+///
+/// # Errors
+///
+/// Returns an error message if the date is not a valid `YYYY-MM-DD` date.
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_format::{DateField, Timezone, DEFAULT_DATE_PATTERN};
+    use crate::model::ConversationItem;
+
+    fn conversation() -> Conversation {
+        let mut answer = ConversationItem::new(
+            "See the Rust book.".to_string(),
+            "assistant".to_string(),
+            0.0,
+        );
+        answer.model = Some("o1".to_string());
+        let mut conversation = Conversation::new(
+            "Learning Rust".to_string(),
+            vec![
+                ConversationItem::new("How do I learn Rust?".to_string(), "user".to_string(), 0.0),
+                answer,
+            ],
+            String::new(),
+        );
+        conversation.id = Some("c1".to_string());
+        conversation.create_time = 1672527600.0; // 2022-12-31T23:00:00Z
+        conversation.update_time = 1672617600.0; // 2023-01-02T00:00:00Z
+        conversation.model = Some("gpt-4o".to_string());
+        conversation.gizmo_id = Some("g-p-client".to_string());
+        conversation
+    }
+
+    #[test]
+    fn test_filter_criteria() {
+        let conversation = conversation();
+        assert!(Filter::default().matches(&conversation));
+
+        let filter = |filter: Filter| filter.matches(&conversation);
+        assert!(filter(Filter {
+            title: Some(Regex::new("(?i)^learning").unwrap()),
+            keywords: vec!["RUST BOOK".to_string()],
+            min_turns: 1,
+            models: vec!["o1".to_string()],
+            gizmos: vec!["g-p-client".to_string()],
+            ids: vec!["c1".to_string(), "c2".to_string()],
+            archived: Archived::Exclude,
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            title: Some(Regex::new("Python").unwrap()),
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            keywords: vec!["python".to_string()],
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            min_turns: 2,
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            models: vec!["gpt-3.5".to_string()],
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            gizmos: vec!["g-p-other".to_string()],
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            archived: Archived::Only,
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            excluded_ids: vec!["c1".to_string()],
+            ..Filter::default()
+        }));
+        assert!(!filter(Filter {
+            ids: vec!["c2".to_string()],
+            ..Filter::default()
+        }));
+    }
+
+    #[test]
+    fn test_filter_dates() {
+        let conversation = conversation();
+        let date = |date: &str| Some(parse_date(date).unwrap());
+        let updated = Filter {
+            since: date("2023-01-02"),
+            until: date("2023-01-02"),
+            ..Filter::default()
+        };
+        assert!(updated.matches(&conversation));
+
+        let created = Filter {
+            date_format: DateFormat::new(Timezone::Utc, DEFAULT_DATE_PATTERN, DateField::Created)
+                .unwrap(),
+            ..updated.clone()
+        };
+        assert!(!created.matches(&conversation));
+
+        let paris = Filter {
+            until: date("2023-01-01"),
+            date_format: DateFormat::new(
+                Timezone::Named(chrono_tz::Tz::Europe__Paris),
+                DEFAULT_DATE_PATTERN,
+                DateField::Created,
+            )
+            .unwrap(),
+            ..created
+        };
+        assert!(!paris.matches(&conversation));
+        assert!(Filter {
+            since: None,
+            ..paris
+        }
+        .matches(&conversation));
+
+        assert!(parse_date("2023-02-30").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    date_format::DateFormat,
+    model::{Conversation, ItemKind},
+};
+use clap::ValueEnum;
+
+/// Syntax of the front matter block heading the Markdown files, selectable from the command line.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum FrontMatter {
+    /// YAML between `---` lines, as read by Obsidian, Jekyll or Hugo
+    Yaml,
+    /// TOML between `+++` lines, as read by Hugo or Zola
+    Toml,
+}
+
+/// A value of the front matter.
+enum Field {
+    String(String),
+    DateTime(String),
+    Integer(usize),
+}
+
+/// Renders the metadata of a `Conversation` as a front matter block.
+///
+/// The block holds the title, id, creation and update times (RFC 3339), model slug and
+/// custom GPT id of the conversation, its branch position, and the number of messages it
+/// contains, overall and by author, the reasoning of the answers and the system messages not
+/// counting as messages.
+/// Fields the export does not provide are left out.
+///
+/// # Arguments
+///
+/// * `conversation` - The `Conversation` whose metadata is rendered.
+///
+/// * `format` - The `FrontMatter` syntax of the block.
+///
+/// * `date_format` - The `DateFormat` giving the time zone of the creation and update times.
+///
+/// # Returns
+///
+/// A `String` holding the block, delimiters included, followed by an empty line.
+pub fn front_matter(
+    conversation: &Conversation,
+    format: FrontMatter,
+    date_format: &DateFormat,
+) -> String {
+    let mut fields = vec![("title", Field::String(conversation.title.clone()))];
+    if let Some(id) = &conversation.id {
+        fields.push(("id", Field::String(id.clone())));
+    }
+    if conversation.create_time > 0.0 {
+        let created = date_format.datetime(conversation.create_time);
+        fields.push(("created", Field::DateTime(created)));
+    }
+    if conversation.update_time > 0.0 {
+        let updated = date_format.datetime(conversation.update_time);
+        fields.push(("updated", Field::DateTime(updated)));
+    }
+    if let Some(model) = &conversation.model {
+        fields.push(("model", Field::String(model.clone())));
+    }
+    if let Some(gizmo_id) = &conversation.gizmo_id {
+        fields.push(("gizmo_id", Field::String(gizmo_id.clone())));
+    }
+    if let Some(branch) = conversation.branch {
+        fields.push(("branch", Field::Integer(branch.index)));
+        fields.push(("branches", Field::Integer(branch.count)));
+    }
+    // The reasoning of the answers and the system messages are not messages of the conversation.
+    let messages = || {
+        conversation.items.iter().filter(|item| {
+            !matches!(
+                item.kind,
+                ItemKind::Reasoning { .. } | ItemKind::SystemPrompt | ItemKind::CustomInstructions
+            )
+        })
+    };
+    let count = |author: &str| messages().filter(|item| item.author == author).count();
+    fields.push(("messages", Field::Integer(messages().count())));
+    fields.push(("user_messages", Field::Integer(count("user"))));
+    fields.push(("assistant_messages", Field::Integer(count("assistant"))));
+
+    let (delimiter, separator) = match format {
+        FrontMatter::Yaml => ("---", ": "),
+        FrontMatter::Toml => ("+++", " = "),
+    };
+    let mut block = format!("{}\n", delimiter);
+    for (key, value) in fields {
+        let value = match value {
+            // JSON strings are valid double-quoted strings in both YAML and TOML.
+            Field::String(text) => serde_json::Value::String(text).to_string(),
+            Field::DateTime(datetime) => datetime,
+            Field::Integer(number) => number.to_string(),
+        };
+        block.push_str(&format!("{}{}{}\n", key, separator, value));
+    }
+    block.push_str(&format!("{}\n\n", delimiter));
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_format::{DateField, Timezone, DEFAULT_DATE_PATTERN};
+    use crate::model::{Branch, ConversationItem};
+    use chrono_tz::Tz;
+
+    fn conversation() -> Conversation {
+        let mut conversation = Conversation::new(
+            "Quotes: \"and\" backslashes \\".to_string(),
+            vec![
+                ConversationItem::new("Hello!".to_string(), "user".to_string(), 0.0),
+                ConversationItem::new("Hi!".to_string(), "assistant".to_string(), 0.0),
+                ConversationItem::new("42".to_string(), "python".to_string(), 0.0),
+            ],
+            "2023-01-01".to_string(),
+        );
+        conversation.id = Some("c1".to_string());
+        conversation.create_time = 1672531200.0;
+        conversation.update_time = 1672534800.0;
+        conversation.model = Some("gpt-4o".to_string());
+        conversation
+    }
+
+    #[test]
+    fn test_front_matter_yaml() {
+        let expected = r#"---
+title: "Quotes: \"and\" backslashes \\"
+id: "c1"
+created: 2023-01-01T00:00:00Z
+updated: 2023-01-01T01:00:00Z
+model: "gpt-4o"
+messages: 3
+user_messages: 1
+assistant_messages: 1
+---
+
+"#;
+        assert_eq!(
+            front_matter(&conversation(), FrontMatter::Yaml, &DateFormat::default()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_front_matter_toml() {
+        let mut conversation = conversation();
+        conversation.id = None;
+        conversation.gizmo_id = Some("g-123".to_string());
+        conversation.branch = Some(Branch::new(2, 3));
+        let mut reasoning = ConversationItem::new("Hm.".to_string(), "assistant".to_string(), 0.0);
+        reasoning.kind = ItemKind::Reasoning { recap: None };
+        conversation.items.insert(1, reasoning);
+        let mut prompt = ConversationItem::new("Be nice.".to_string(), "system".to_string(), 0.0);
+        prompt.kind = ItemKind::SystemPrompt;
+        conversation.items.insert(0, prompt);
+        let paris = DateFormat::new(
+            Timezone::Named(Tz::Europe__Paris),
+            DEFAULT_DATE_PATTERN,
+            DateField::Updated,
+        )
+        .unwrap();
+
+        let expected = r#"+++
+title = "Quotes: \"and\" backslashes \\"
+created = 2023-01-01T01:00:00+01:00
+updated = 2023-01-01T02:00:00+01:00
+model = "gpt-4o"
+gizmo_id = "g-123"
+branch = 2
+branches = 3
+messages = 3
+user_messages = 1
+assistant_messages = 1
++++
+
+"#;
+        assert_eq!(
+            front_matter(&conversation, FrontMatter::Toml, &paris),
+            expected
+        );
+    }
+}
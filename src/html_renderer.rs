@@ -0,0 +1,405 @@
+use crate::{
+    exporter::{ExportOptions, Exporter, IndexEntry},
+    markdown_renderer::{item_annotation, item_to_md, section_title},
+    model::{Conversation, ItemKind},
+};
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::sync::OnceLock;
+use syntect::{highlighting::ThemeSet, html::highlighted_html_for_string, parsing::SyntaxSet};
+
+/// Theme used to highlight code blocks. It ships with syntect, so no network access is needed.
+const CODE_THEME: &str = "InspiredGitHub";
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; line-height: 1.5; color: #1f2328; background: #f6f8fa; margin: 0; }
+main { max-width: 52rem; margin: 0 auto; padding: 2rem 1rem; }
+h1 { font-size: 1.6rem; margin-bottom: 0.25rem; }
+.meta { color: #59636e; margin-top: 0; }
+.turn { border-radius: 0.5rem; padding: 0.75rem 1rem; margin: 1rem 0; }
+.turn h2 { font-size: 0.8rem; text-transform: uppercase; letter-spacing: 0.05em; margin: 0 0 0.5rem; }
+.turn.user { background: #ddf4ff; border: 1px solid #b6e3ff; margin-left: 3rem; }
+.turn.user h2 { color: #0969da; }
+.turn.assistant { background: #ffffff; border: 1px solid #d1d9e0; margin-right: 3rem; }
+.turn.assistant h2 { color: #1a7f37; }
+.turn.tool { background: #f6f8fa; border: 1px dashed #d1d9e0; margin-right: 3rem; }
+.turn.tool h2 { color: #8250df; }
+.turn.system { background: #fff8c5; border: 1px solid #eac54f; }
+.turn.system h2 { color: #9a6700; }
+blockquote { margin: 0; padding-left: 1rem; border-left: 0.25rem solid #d1d9e0; }
+.turn.reasoning { background: #f6f8fa; border: 1px solid #d1d9e0; margin-right: 3rem; color: #59636e; }
+.turn.reasoning summary { cursor: pointer; font-size: 0.8rem; text-transform: uppercase; letter-spacing: 0.05em; color: #9a6700; }
+pre { padding: 0.75rem; overflow-x: auto; border: 1px solid #d1d9e0; border-radius: 0.375rem; font-size: 0.85rem; }
+img { max-width: 100%; border-radius: 0.375rem; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+table { border-collapse: collapse; }
+.footnote-definition { font-size: 0.85rem; color: #59636e; }
+.footnote-definition p { display: inline; margin-left: 0.25rem; }
+th, td { border: 1px solid #d1d9e0; padding: 0.25rem 0.5rem; }
+ul.index { list-style: none; padding: 0; }
+ul.index li { background: #ffffff; border: 1px solid #d1d9e0; border-radius: 0.375rem; margin: 0.5rem 0; padding: 0.5rem 1rem; }
+ul.index .date { color: #59636e; font-variant-numeric: tabular-nums; margin-right: 1rem; }
+"#;
+
+/// `Exporter` writing each conversation as a self-contained HTML page, plus an `index.html`
+/// page linking to all of them.
+pub struct HtmlExporter {
+    /// Options of the export.
+    pub options: ExportOptions,
+}
+
+impl Exporter for HtmlExporter {
+    fn extension(&self) -> &str {
+        "html"
+    }
+
+    fn render(&self, conversation: &Conversation) -> Vec<u8> {
+        conversation_to_html(conversation, &self.options).into_bytes()
+    }
+
+    fn render_index(&self, entries: &[IndexEntry]) -> Option<Vec<u8>> {
+        Some(index_to_html(entries).into_bytes())
+    }
+}
+
+/// Converts a `Conversation` object into a self-contained HTML document.
+///
+/// The text of each item is interpreted as Markdown. Fenced code blocks are syntax-highlighted
+/// with inline styles, and raw HTML found in the messages is escaped so that it is displayed
+/// rather than interpreted. The footnotes of cited sources link to their definitions, which keep
+/// the numbers of the Markdown output. User, assistant and tool turns are styled distinctly, and
+/// reasoning is folded in a `<details>` element; the stylesheet is inlined so the file can be
+/// opened and shared on its own.
+///
+/// # Arguments
+///
+/// * `conversation` - A reference to the `Conversation` to render.
+///
+/// * `options` - The `ExportOptions` of the export. With `annotate`, each turn shows the time of
+///   its message and, for answers, the model that wrote it (see `item_annotation`). With
+///   `anchors`, the title and each turn carry the id of the conversation or message in the
+///   export as their `id` attribute, so that links can point to them.
+///
+/// # Returns
+///
+/// A `String` holding the complete HTML document.
+pub fn conversation_to_html(conversation: &Conversation, options: &ExportOptions) -> String {
+    let id = |id: &Option<String>| match id {
+        Some(id) if options.anchors => format!(" id=\"{}\"", escape_html(id)),
+        _ => String::new(),
+    };
+    let mut body = format!(
+        "<h1{}>{}</h1>\n",
+        id(&conversation.id),
+        escape_html(&conversation.title)
+    );
+    let mut meta = conversation.date.clone();
+    if let Some(branch) = conversation.branch {
+        meta.push_str(&format!(" · Branch {} of {}", branch.index, branch.count));
+    }
+    body.push_str(&format!("<p class=\"meta\">{}</p>\n", escape_html(&meta)));
+
+    for item in &conversation.items {
+        let class = match item.kind {
+            ItemKind::Text if item.author == "user" => "user",
+            ItemKind::Text => "assistant",
+            ItemKind::Code { .. } | ItemKind::ExecutionOutput => "tool",
+            ItemKind::Reasoning { .. } => "reasoning",
+            ItemKind::SystemPrompt | ItemKind::CustomInstructions => "system",
+        };
+        let annotation = options
+            .annotate
+            .then(|| item_annotation(item, &options.date_format))
+            .flatten()
+            .map(|annotation| format!("<p class=\"meta\">{}</p>\n", escape_html(&annotation)))
+            .unwrap_or_default();
+        // Reasoning is folded, with its title as the summary of the block.
+        let (open, close, fold_end) = match item.kind {
+            ItemKind::Reasoning { .. } => ("<details>\n<summary>", "</summary>\n", "</details>\n"),
+            _ => ("<h2>", "</h2>\n", ""),
+        };
+        body.push_str(&format!(
+            "<section{} class=\"turn {}\">\n{}{}{}{}{}{}</section>\n",
+            id(&item.id),
+            class,
+            open,
+            escape_html(&section_title(item)),
+            close,
+            annotation,
+            markdown_to_html(&item_to_md(item)),
+            fold_end
+        ));
+    }
+    html_document(&conversation.title, &body)
+}
+
+/// Builds the `index.html` page linking to every exported conversation.
+///
+/// Entries are listed from the most recently updated conversation to the oldest, then by title.
+/// Sorting on the update time rather than on the date keeps this order whatever the date format.
+///
+/// # Arguments
+///
+/// * `entries` - A slice of `IndexEntry`, the conversations written to the output folder.
+///
+/// # Returns
+///
+/// A `String` holding the complete HTML document.
+pub fn index_to_html(entries: &[IndexEntry]) -> String {
+    let mut entries: Vec<&IndexEntry> = entries.iter().collect();
+    entries.sort_by(|entry1, entry2| {
+        entry2
+            .update_time
+            .total_cmp(&entry1.update_time)
+            .then_with(|| entry1.title.cmp(&entry2.title))
+            .then_with(|| entry1.filename.cmp(&entry2.filename))
+    });
+
+    let mut body = String::from("<h1>Conversations</h1>\n<ul class=\"index\">\n");
+    for entry in &entries {
+        body.push_str(&format!(
+            "<li><span class=\"date\">{}</span><a href=\"{}\">{}</a></li>\n",
+            escape_html(&entry.date),
+            escape_html(&entry.filename),
+            escape_html(&entry.title)
+        ));
+    }
+    body.push_str("</ul>\n");
+    html_document("Conversations", &body)
+}
+
+fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+/// Renders Markdown text to HTML, highlighting fenced and indented code blocks.
+///
+/// Footnotes are labeled by their own label rather than renumbered from 1, since the citations
+/// of a conversation are numbered across its items, each rendered separately.
+fn markdown_to_html(text: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((language, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, code)) = code_block.take() {
+                    events.push(Event::Html(highlight_code(&code, &language).into()));
+                }
+            }
+            Event::Text(text) => match code_block.as_mut() {
+                Some((_, code)) => code.push_str(&text),
+                None => events.push(Event::Text(text)),
+            },
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::FootnoteReference(label) => {
+                let label = escape_html(&label);
+                events.push(Event::InlineHtml(
+                    format!(
+                        "<sup class=\"footnote-reference\"><a href=\"#fn-{}\">{}</a></sup>",
+                        label, label
+                    )
+                    .into(),
+                ));
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                let label = escape_html(&label);
+                events.push(Event::Html(
+                    format!(
+                        "<div class=\"footnote-definition\" id=\"fn-{}\"><sup>{}</sup>\n",
+                        label, label
+                    )
+                    .into(),
+                ));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => events.push(Event::Html("</div>\n".into())),
+            event => events.push(event),
+        }
+    }
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    output
+}
+
+/// Highlights a code snippet as an HTML `<pre>` block with inline styles.
+///
+/// The syntax is looked up from the fence language (name or file extension); unknown languages
+/// are rendered as plain text.
+fn highlight_code(code: &str, language: &str) -> String {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme = &THEMES.get_or_init(ThemeSet::load_defaults).themes[CODE_THEME];
+
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    highlighted_html_for_string(code, syntaxes, syntax, theme)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>\n", escape_html(code)))
+}
+
+/// Escapes the characters of `text` that HTML would interpret, for element content and
+/// attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Citation, Conversation, ConversationItem};
+
+    #[test]
+    fn test_conversation_to_html() {
+        let conversation = Conversation::new(
+            "Rust <3".to_string(),
+            vec![
+                ConversationItem::new("How do I print?".to_string(), "user".to_string(), 0.0),
+                ConversationItem::new(
+                    "Use a macro:\n\n```rust\nprintln!(\"hi\");\n```\n<script>alert(1)</script>"
+                        .to_string(),
+                    "assistant".to_string(),
+                    1.0,
+                ),
+            ],
+            "2023-01-01".to_string(),
+        );
+
+        let html = conversation_to_html(&conversation, &ExportOptions::default());
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Rust &lt;3</title>"));
+        assert!(html
+            .contains("<section class=\"turn user\">\n<h2>Question</h2>\n<p>How do I print?</p>"));
+        assert!(html.contains("<section class=\"turn assistant\">"));
+        assert!(html.contains("<pre style=\"background-color:"));
+        assert!(html.contains("<span style=\"color:"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_conversation_to_html_anchors() {
+        let mut question = ConversationItem::new("Hello!".to_string(), "user".to_string(), 0.0);
+        question.id = Some("m1".to_string());
+        let mut conversation =
+            Conversation::new("Hi".to_string(), vec![question], "2023-01-01".to_string());
+        conversation.id = Some("c1".to_string());
+        let options = ExportOptions {
+            anchors: true,
+            ..ExportOptions::default()
+        };
+
+        let html = conversation_to_html(&conversation, &options);
+
+        assert!(html.contains("<h1 id=\"c1\">Hi</h1>"));
+        assert!(html.contains("<section id=\"m1\" class=\"turn user\">"));
+        let html = conversation_to_html(&conversation, &ExportOptions::default());
+        assert!(!html.contains("id=\"m1\""));
+    }
+
+    #[test]
+    fn test_conversation_to_html_reasoning() {
+        let mut reasoning = ConversationItem::new(
+            "Checking the *date*.".to_string(),
+            "assistant".to_string(),
+            0.0,
+        );
+        reasoning.kind = ItemKind::Reasoning { recap: None };
+        let conversation = Conversation::new(
+            "Rust".to_string(),
+            vec![reasoning],
+            "2023-01-01".to_string(),
+        );
+
+        let html = conversation_to_html(&conversation, &ExportOptions::default());
+
+        assert!(html.contains(
+            "<section class=\"turn reasoning\">\n<details>\n<summary>Reasoning</summary>\n\
+             <p>Checking the <em>date</em>.</p>\n</details>\n</section>"
+        ));
+    }
+
+    #[test]
+    fn test_conversation_to_html_citations() {
+        let mut answer = ConversationItem::new(
+            "Released in 2015[^3].".to_string(),
+            "assistant".to_string(),
+            0.0,
+        );
+        answer.citations = vec![Citation {
+            number: 3,
+            title: Some("Rust <1.0>".to_string()),
+            url: "https://blog.rust-lang.org/".to_string(),
+        }];
+        let conversation =
+            Conversation::new("Rust".to_string(), vec![answer], "2023-01-01".to_string());
+
+        let html = conversation_to_html(&conversation, &ExportOptions::default());
+
+        assert!(html.contains(
+            "Released in 2015<sup class=\"footnote-reference\"><a href=\"#fn-3\">3</a></sup>."
+        ));
+        assert!(html.contains("<div class=\"footnote-definition\" id=\"fn-3\"><sup>3</sup>"));
+        assert!(html.contains("<a href=\"https://blog.rust-lang.org/\">Rust &lt;1.0&gt;</a>"));
+    }
+
+    #[test]
+    fn test_index_to_html() {
+        let html = index_to_html(&[
+            IndexEntry {
+                title: "Older".to_string(),
+                date: "2023-01-01".to_string(),
+                update_time: 1672531200.0,
+                filename: "2023-01-01-Older.html".to_string(),
+            },
+            IndexEntry {
+                title: "Newer & better".to_string(),
+                date: "2023-02-01".to_string(),
+                update_time: 1675209600.0,
+                filename: "2023-02-01-Newer__better.html".to_string(),
+            },
+        ]);
+
+        let newer = html
+            .find("<a href=\"2023-02-01-Newer__better.html\">Newer &amp; better</a>")
+            .unwrap();
+        let older = html
+            .find("<a href=\"2023-01-01-Older.html\">Older</a>")
+            .unwrap();
+        assert!(newer < older);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{claude_converter::ClaudeImporter, converter::ChatGptImporter, model::Conversation};
+use clap::ValueEnum;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use std::{fmt, io::Read};
+
+/// A source of conversations, such as the data export of a chat assistant.
+///
+/// An export is a JSON array holding one object per conversation. An `Importer` recognizes the
+/// shape of these objects and converts each of them into the target `Conversation` model, so
+/// the rest of the pipeline does not depend on the tool the conversations come from.
+pub trait Importer: Send + Sync {
+    /// Returns the name of the source tool, as accepted by the `--source` option.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether a conversation, given as raw JSON, has the shape produced by this source.
+    fn detect(&self, conversation: &Value) -> bool;
+
+    /// Converts a single conversation, given as raw JSON, into one or more `Conversation`s
+    /// (several when every branch of the conversation is exported).
+    fn import(
+        &self,
+        conversation: Value,
+        options: &ImportOptions,
+    ) -> Result<Vec<Conversation>, serde_json::Error>;
+}
+
+/// Options shared by every `Importer`.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ImportOptions {
+    /// Produce one `Conversation` per branch of edited or regenerated conversations.
+    pub all_branches: bool,
+    /// Keep the reasoning of the assistant (o1/o3 thoughts, Claude extended thinking) as items of
+    /// kind `ItemKind::Reasoning`.
+    pub reasoning: bool,
+    /// Keep the system prompts and custom instructions that precede ChatGPT conversations, as
+    /// items of kind `ItemKind::SystemPrompt` and `ItemKind::CustomInstructions`.
+    pub system_messages: bool,
+}
+
+/// Source of the input file, selectable from the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum InputFormat {
+    /// Detect the source from the content of the file
+    #[default]
+    Auto,
+    /// ChatGPT data export (conversations.json)
+    Chatgpt,
+    /// Claude.ai data export (conversations.json)
+    Claude,
+}
+
+impl InputFormat {
+    /// Returns the `Importer` for this format, sniffing `first_conversation` when the format is
+    /// `Auto`.
+    ///
+    /// Detection looks at the first conversation of the export only. `None` is returned when no
+    /// importer recognizes it.
+    pub fn importer(self, first_conversation: &Value) -> Option<&'static dyn Importer> {
+        match self {
+            InputFormat::Auto => detect(first_conversation),
+            InputFormat::Chatgpt => Some(&ChatGptImporter),
+            InputFormat::Claude => Some(&ClaudeImporter),
+        }
+    }
+}
+
+/// Every built-in importer, in detection order.
+const IMPORTERS: &[&dyn Importer] = &[&ChatGptImporter, &ClaudeImporter];
+
+fn detect(conversation: &Value) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .copied()
+        .find(|importer| importer.detect(conversation))
+}
+
+/// Streams the conversations of an export, one at a time.
+///
+/// The export is a JSON array read from `reader`. Each of its elements is parsed into a raw
+/// `Value` and handed to `f` before the next one is read, so that memory usage is bounded by
+/// the size of the largest conversation rather than the size of the export.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the JSON document. It should be buffered.
+///
+/// * `f` - A closure called with the index and raw JSON of each conversation. Returning an
+///   error stops the parsing.
+///
+/// # Returns
+///
+/// The number of conversations read.
+///
+/// # Errors
+///
+/// Returns a `serde_json::Error` if the document is not a JSON array, or with the message of
+/// the error returned by `f`.
+pub fn for_each_conversation<R, F>(reader: R, f: F) -> serde_json::Result<usize>
+where
+    R: Read,
+    F: FnMut(usize, Value) -> Result<(), String>,
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let count = deserializer.deserialize_seq(ConversationsVisitor(f))?;
+    deserializer.end()?;
+    Ok(count)
+}
+
+/// Reads every conversation of an export into memory.
+///
+/// This is the in-memory counterpart of `export::export`, for exports small enough to be held
+/// in memory: the importer is chosen by `format` (sniffing the first conversation for
+/// `InputFormat::Auto`), and each conversation is converted and tagged with the name of its
+/// importer in `Conversation::source`.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the JSON document, such as a buffered `conversations.json` file or
+///   the reader returned by `ExportSource::conversations_reader`.
+///
+/// * `format` - The `InputFormat` of the export.
+///
+/// * `options` - The `ImportOptions` of the conversion.
+///
+/// # Errors
+///
+/// Returns a `serde_json::Error` if the document is not a JSON array, if its format is not
+/// recognized, or if one of its conversations cannot be converted, in which case the message
+/// names the conversation.
+pub fn read_conversations<R: Read>(
+    reader: R,
+    format: InputFormat,
+    options: &ImportOptions,
+) -> serde_json::Result<Vec<Conversation>> {
+    let mut importer = None;
+    let mut conversations = Vec::new();
+    for_each_conversation(reader, |index, raw_conversation| {
+        let importer = match importer {
+            Some(importer) => importer,
+            None => *importer.insert(
+                format
+                    .importer(&raw_conversation)
+                    .ok_or("unrecognized conversation format")?,
+            ),
+        };
+        let imported = importer
+            .import(raw_conversation, options)
+            .map_err(|e| format!("{} conversation #{}: {}", importer.name(), index + 1, e))?;
+        for mut conversation in imported {
+            conversation.source = Some(importer.name());
+            conversations.push(conversation);
+        }
+        Ok(())
+    })?;
+    Ok(conversations)
+}
+
+struct ConversationsVisitor<F>(F);
+
+impl<'de, F> Visitor<'de> for ConversationsVisitor<F>
+where
+    F: FnMut(usize, Value) -> Result<(), String>,
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of conversations")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<usize, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut count = 0;
+        while let Some(conversation) = seq.next_element::<Value>()? {
+            (self.0)(count, conversation).map_err(de::Error::custom)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_importer_detection() {
+        let chatgpt = json!({"title": "t", "mapping": {}});
+        let importer = InputFormat::Auto.importer(&chatgpt).unwrap();
+        assert_eq!(importer.name(), "chatgpt");
+
+        let claude = json!({"uuid": "u", "name": "t", "chat_messages": []});
+        let importer = InputFormat::Auto.importer(&claude).unwrap();
+        assert_eq!(importer.name(), "claude");
+
+        let unknown = json!({"title": "t", "messages": []});
+        assert!(InputFormat::Auto.importer(&unknown).is_none());
+        assert_eq!(
+            InputFormat::Chatgpt.importer(&unknown).unwrap().name(),
+            "chatgpt"
+        );
+    }
+
+    #[test]
+    fn test_for_each_conversation() {
+        let input = r#"[{"title": "a"}, {"title": "b"}, {"title": "c"}]"#;
+        let mut titles = Vec::new();
+
+        let count = for_each_conversation(input.as_bytes(), |index, conversation| {
+            titles.push(format!(
+                "{}:{}",
+                index,
+                conversation["title"].as_str().unwrap()
+            ));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count, 3);
+        assert_eq!(titles, vec!["0:a", "1:b", "2:c"]);
+    }
+
+    #[test]
+    fn test_for_each_conversation_errors() {
+        let stopped = for_each_conversation(r#"[{}, {}]"#.as_bytes(), |index, _| {
+            if index == 1 {
+                Err("stop".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(stopped.unwrap_err().to_string().starts_with("stop"));
+
+        let truncated = for_each_conversation(r#"[{}, {"#.as_bytes(), |_, _| Ok(()));
+        assert!(truncated.unwrap_err().is_eof());
+
+        let not_an_array = for_each_conversation(r#"{"title": "a"}"#.as_bytes(), |_, _| Ok(()));
+        assert!(not_an_array.is_err());
+    }
+
+    #[test]
+    fn test_read_conversations() {
+        let input = r#"[
+            {"uuid": "c1", "name": "First", "created_at": "2024-03-01T10:00:00Z",
+             "updated_at": "2024-03-01T10:00:00Z", "chat_messages": []},
+            {"uuid": "c2", "name": "Second", "created_at": "2024-03-02T10:00:00Z",
+             "updated_at": "2024-03-02T10:00:00Z", "chat_messages": []}
+        ]"#;
+        let conversations = read_conversations(
+            input.as_bytes(),
+            InputFormat::Auto,
+            &ImportOptions::default(),
+        )
+        .unwrap();
+        let titles: Vec<&str> = conversations.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["First", "Second"]);
+        assert_eq!(conversations[1].source, Some("claude"));
+
+        let invalid = read_conversations(
+            r#"[{"mapping": {}}]"#.as_bytes(),
+            InputFormat::Chatgpt,
+            &ImportOptions::default(),
+        );
+        assert!(invalid
+            .unwrap_err()
+            .to_string()
+            .starts_with("chatgpt conversation #1: missing field `title`"));
+    }
+}
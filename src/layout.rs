@@ -0,0 +1,194 @@
+use crate::{date_format::DateFormat, model::Conversation, utils::normalized_filename_string};
+
+/// Placeholders of a layout pattern, as listed in the help of the `--layout` option.
+const PLACEHOLDERS: &[&str] = &["year", "month", "day", "gizmo", "source"];
+
+/// Value of `{gizmo}` for conversations that were not held with a custom GPT or project.
+const NO_GIZMO: &str = "default";
+
+/// Value of the placeholders that are empty once normalized, such as the date of a conversation
+/// whose timestamp is out of range or the source of a conversation that was not imported.
+const UNKNOWN: &str = "unknown";
+
+/// Organization of the exported files into subfolders of the output folder.
+///
+/// The layout is a path pattern such as `{year}/{month}` or `{source}/{gizmo}`, in which the
+/// following placeholders are replaced for each conversation:
+/// - `{year}`, `{month}` and `{day}`: the date of the conversation, in the time zone and from the
+///   timestamp chosen by its `DateFormat`;
+/// - `{gizmo}`: the id of the custom GPT or project of the conversation, or "default";
+/// - `{source}`: the name of the importer the conversation comes from, e.g. "chatgpt".
+///
+/// The default layout is empty, which writes every file directly in the output folder.
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    /// Components of the pattern, without empty ones.
+    components: Vec<String>,
+    date_format: DateFormat,
+}
+
+impl Layout {
+    /// Parses a layout pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The path pattern, with `/` separating folders.
+    ///
+    /// * `date_format` - The `DateFormat` giving the date of the conversations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the pattern contains an unknown or unterminated placeholder,
+    /// or a `..` component that would lead out of the output folder.
+    pub fn new(pattern: &str, date_format: DateFormat) -> Result<Layout, String> {
+        let components: Vec<String> = pattern
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(str::to_string)
+            .collect();
+        for component in &components {
+            if component == ".." {
+                return Err(format!(
+                    "layout '{}' leads out of the output folder",
+                    pattern
+                ));
+            }
+            let mut rest = component.as_str();
+            while let Some(start) = rest.find('{') {
+                let Some(end) = rest[start..].find('}') else {
+                    return Err(format!("unterminated placeholder in layout '{}'", pattern));
+                };
+                let placeholder = &rest[start + 1..start + end];
+                if !PLACEHOLDERS.contains(&placeholder) {
+                    return Err(format!(
+                        "unknown placeholder '{{{}}}' in layout '{}', expected one of {{{}}}",
+                        placeholder,
+                        pattern,
+                        PLACEHOLDERS.join("}, {")
+                    ));
+                }
+                rest = &rest[start + end + 1..];
+            }
+        }
+        Ok(Layout {
+            components,
+            date_format,
+        })
+    }
+
+    /// Returns the folder of a conversation, relative to the output folder.
+    ///
+    /// # Returns
+    ///
+    /// A `String` holding the folder with `/` separators and a trailing `/`, or an empty string
+    /// for the default layout. Placeholder values are normalized like file names, keeping their
+    /// dashes, so that they cannot split into several folders or lead out of the output folder.
+    /// Values left empty, such as `..`, are replaced with "unknown".
+    pub fn folder(&self, conversation: &Conversation) -> String {
+        let mut folder = String::new();
+        for component in &self.components {
+            let mut component = component.clone();
+            for placeholder in PLACEHOLDERS {
+                let key = format!("{{{}}}", placeholder);
+                if component.contains(&key) {
+                    let value = normalized_value(&self.value(conversation, placeholder));
+                    component = component.replace(&key, &value);
+                }
+            }
+            folder.push_str(&component);
+            folder.push('/');
+        }
+        folder
+    }
+
+    fn value(&self, conversation: &Conversation, placeholder: &str) -> String {
+        match placeholder {
+            "year" => self.date_format.format_conversation(conversation, "%Y"),
+            "month" => self.date_format.format_conversation(conversation, "%m"),
+            "day" => self.date_format.format_conversation(conversation, "%d"),
+            "gizmo" => conversation
+                .gizmo_id
+                .clone()
+                .unwrap_or_else(|| NO_GIZMO.to_string()),
+            "source" => conversation.source.unwrap_or_default().to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Normalizes each dash-separated part of a placeholder value with `normalized_filename_string`,
+/// or returns "unknown" if nothing is left of the value.
+fn normalized_value(value: &str) -> String {
+    let normalized = value
+        .split('-')
+        .map(|part| normalized_filename_string(part, 40))
+        .collect::<Vec<_>>()
+        .join("-");
+    if normalized.chars().all(|c| c == '-') {
+        UNKNOWN.to_string()
+    } else {
+        normalized
+    }
+}
+
+/// Returns the relative path leading from a folder returned by `Layout::folder` back to the
+/// output folder, e.g. `../../` for `2023/01/`.
+pub(crate) fn path_to_root(folder: &str) -> String {
+    "../".repeat(folder.matches('/').count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Conversation {
+        let mut conversation = Conversation::new("Title".to_string(), Vec::new(), String::new());
+        conversation.update_time = 1675209600.0; // 2023-02-01
+        conversation.gizmo_id = Some("g-p-123".to_string());
+        conversation.source = Some("chatgpt");
+        conversation
+    }
+
+    #[test]
+    fn test_layout_folder() {
+        let layout = Layout::new("{year}/{month}", DateFormat::default()).unwrap();
+        assert_eq!(layout.folder(&conversation()), "2023/02/");
+        assert_eq!(path_to_root(&layout.folder(&conversation())), "../../");
+
+        let layout = Layout::new("/archive/{source}-{gizmo}/", DateFormat::default()).unwrap();
+        assert_eq!(layout.folder(&conversation()), "archive/chatgpt-g-p-123/");
+
+        let mut conversation = conversation();
+        conversation.gizmo_id = None;
+        let layout = Layout::new("{gizmo}", DateFormat::default()).unwrap();
+        assert_eq!(layout.folder(&conversation), "default/");
+
+        assert_eq!(Layout::default().folder(&conversation), "");
+        assert_eq!(path_to_root(""), "");
+    }
+
+    #[test]
+    fn test_layout_folder_stays_in_output_folder() {
+        let layout = Layout::new("{source}/{gizmo}/{year}", DateFormat::default()).unwrap();
+        let mut conversation = conversation();
+        conversation.source = None;
+        conversation.update_time = f64::MAX;
+        for (gizmo, expected) in [
+            ("..", "unknown/unknown/unknown/"),
+            ("", "unknown/unknown/unknown/"),
+            (".", "unknown/unknown/unknown/"),
+            ("../../etc", "unknown/etc/unknown/"),
+            ("/tmp\\a:b", "unknown/tmpab/unknown/"),
+        ] {
+            conversation.gizmo_id = Some(gizmo.to_string());
+            assert_eq!(layout.folder(&conversation), expected, "gizmo {:?}", gizmo);
+        }
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        assert!(Layout::new("{year}/{week}", DateFormat::default()).is_err());
+        assert!(Layout::new("{year", DateFormat::default()).is_err());
+        assert!(Layout::new("../{year}", DateFormat::default()).is_err());
+    }
+}
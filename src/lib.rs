@@ -0,0 +1,113 @@
+//! Conversion of ChatGPT and Claude.ai data exports into Markdown and HTML files.
+//!
+//! The `herodote` command line tool is a thin wrapper around this library, which can be used to
+//! convert exports in-process:
+//!
+//! - `importer` reads the JSON document of an export and converts its conversations into the
+//!   `model::Conversation` model, with `ChatGptImporter` and `ClaudeImporter`. `export_source`
+//!   opens `conversations.json` files and ZIP archives, and resolves images.
+//! - `exporter` renders a `Conversation` in an `exporter::OutputFormat`, with
+//!   `markdown_renderer` and `html_renderer`, customized by `exporter::ExportOptions`
+//!   (`front_matter`, `template`, `date_format`).
+//! - `conversation_writer` writes whole exports to an output folder, organized by a
+//!   `layout::Layout` and tracked by a `manifest::Manifest`, and `filter` selects the
+//!   conversations to export.
+//! - `export` runs these steps as the command line does: `export::export` streams a data export
+//!   into an output folder, as set by an `export::Config`, and returns an `export::Report`.
+//!
+//! The main types and functions of these modules are re-exported from the crate root.
+//!
+//! # Example
+//!
+//! ```
+//! use herodote::{read_conversations, ExportOptions, Exporter, ImportOptions};
+//! use herodote::{InputFormat, OutputFormat};
+//!
+//! let export = r#"[{
+//!     "uuid": "c1",
+//!     "name": "Greetings",
+//!     "created_at": "2024-03-01T10:00:00Z",
+//!     "updated_at": "2024-03-01T10:00:05Z",
+//!     "chat_messages": [
+//!         {"uuid": "m1", "text": "Hello!", "sender": "human",
+//!          "created_at": "2024-03-01T10:00:00Z", "updated_at": "2024-03-01T10:00:00Z"},
+//!         {"uuid": "m2", "text": "Hi!", "sender": "assistant",
+//!          "created_at": "2024-03-01T10:00:05Z", "updated_at": "2024-03-01T10:00:05Z"}
+//!     ]
+//! }]"#;
+//!
+//! let conversations =
+//!     read_conversations(export.as_bytes(), InputFormat::Auto, &ImportOptions::default())?;
+//! let exporter = OutputFormat::Markdown.exporter(&ExportOptions::default());
+//! let markdown = String::from_utf8(exporter.render(&conversations[0]))?;
+//! assert_eq!(markdown, "# Greetings\n\n## Question\nHello!\n\n## Answer\nHi!\n\n");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! A whole data export is written to a folder the way the command line does with `export`:
+//!
+//! ```no_run
+//! use herodote::{export, Config, OutputFormat};
+//!
+//! let mut config = Config::new("export.zip", "output");
+//! config.formats = vec![OutputFormat::Markdown, OutputFormat::Html];
+//! config.prune = true;
+//! let report = export(&config)?;
+//! println!("{} files created", report.changes.created.len());
+//! # Ok::<(), herodote::Error>(())
+//! ```
+
+#![warn(missing_docs)]
+
+/// Footnotes for the sources cited by ChatGPT answers.
+pub(crate) mod citations;
+/// Conversion of Claude.ai exports.
+pub(crate) mod claude_converter;
+/// Writing of whole exports to an output folder.
+pub mod conversation_writer;
+/// Conversion of ChatGPT exports.
+pub(crate) mod converter;
+/// Time zone and patterns of dates and times.
+pub mod date_format;
+/// Errors of an export run.
+pub mod error;
+/// Export runs, from a data export to an output folder.
+pub mod export;
+/// Reading of `conversations.json` files, ZIP archives and their media.
+pub mod export_source;
+/// Output formats and their options.
+pub mod exporter;
+/// Selection of the conversations to export.
+pub mod filter;
+/// Front matter of Markdown files.
+pub mod front_matter;
+/// HTML rendering.
+pub mod html_renderer;
+/// Detection of the source of an export and streaming of its conversations.
+pub mod importer;
+/// Subfolders of the exported files.
+pub mod layout;
+/// Record of the written files, for incremental re-exports.
+pub mod manifest;
+/// Markdown rendering.
+pub mod markdown_renderer;
+/// Structures of the exports and of the target `Conversation` model.
+pub mod model;
+/// User-defined templates of Markdown files.
+pub mod template;
+/// Helpers for file names and dates.
+#[doc(hidden)]
+pub mod utils;
+
+pub use claude_converter::ClaudeImporter;
+pub use conversation_writer::{write, FileNames};
+pub use converter::ChatGptImporter;
+pub use date_format::DateFormat;
+pub use error::Error;
+pub use export::{export, Config, Report};
+pub use exporter::{ExportOptions, Exporter, OutputFormat};
+pub use filter::Filter;
+pub use importer::{read_conversations, ImportOptions, Importer, InputFormat};
+pub use layout::Layout;
+pub use manifest::{Changes, Manifest};
+pub use model::{Branch, Citation, Conversation, ConversationItem, Image, ItemKind};
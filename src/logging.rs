@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use log::{Level, LevelFilter, Record};
+use std::io::Write;
+
+/// Format of the log messages written to the standard error output, selectable from the
+/// command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// One line per message, prefixed with its level, e.g. "warn: ..."
+    #[default]
+    Text,
+    /// One JSON object per line, with the time, level, module and message
+    Json,
+}
+
+/// Returns the level of the messages logged for a number of `-v` and `-q` flags.
+///
+/// Warnings and errors are logged by default. Each `-v` adds a level (info, debug, then trace)
+/// and each `-q` removes one (warnings, then errors).
+pub fn level(verbose: u8, quiet: u8) -> LevelFilter {
+    const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    let index = (2 + verbose as isize - quiet as isize).clamp(0, LEVELS.len() as isize - 1);
+    LEVELS[index as usize]
+}
+
+/// Installs the logger of the application.
+///
+/// The `RUST_LOG` environment variable, if set, overrides `level`, for example to log the debug
+/// messages of a single module with `RUST_LOG=herodote::converter=debug`.
+pub fn init(level: LevelFilter, format: LogFormat) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level).parse_default_env();
+    match format {
+        LogFormat::Text => builder.format(|buf, record| {
+            writeln!(buf, "{}: {}", level_name(record.level()), record.args())
+        }),
+        LogFormat::Json => builder.format(|buf, record| writeln!(buf, "{}", json_record(record))),
+    };
+    builder.init();
+}
+
+fn level_name(level: Level) -> String {
+    level.as_str().to_lowercase()
+}
+
+/// Renders a log record as a single-line JSON object.
+fn json_record(record: &Record) -> String {
+    serde_json::json!({
+        "time": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "level": level_name(record.level()),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, 0), LevelFilter::Warn);
+        assert_eq!(level(2, 0), LevelFilter::Debug);
+        assert_eq!(level(9, 0), LevelFilter::Trace);
+        assert_eq!(level(0, 1), LevelFilter::Error);
+        assert_eq!(level(0, 5), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_json_record() {
+        let record = Record::builder()
+            .level(Level::Warn)
+            .target("herodote::converter")
+            .args(format_args!("Skipped \"node\""))
+            .build();
+        let json: serde_json::Value = serde_json::from_str(&json_record(&record)).unwrap();
+        assert_eq!(json["level"], "warn");
+        assert_eq!(json["target"], "herodote::converter");
+        assert_eq!(json["message"], "Skipped \"node\"");
+        assert!(json["time"].as_str().unwrap().ends_with('Z'));
+    }
+}
@@ -1,48 +1,250 @@
-use clap::Parser;
-use model::GPTInteraction;
+use clap::{ArgAction, Parser};
+use herodote::date_format::{DateField, DateFormat, Timezone, DEFAULT_DATE_PATTERN};
+use herodote::error::{self, Error};
+use herodote::export::{Config, Report};
+use herodote::exporter::OutputFormat;
+use herodote::filter::{self, Archived};
+use herodote::front_matter::FrontMatter;
+use herodote::importer::InputFormat;
+use herodote::layout::Layout;
+use herodote::template::Template;
+use log::{error, warn};
+use logging::LogFormat;
 use std::path::PathBuf;
-use std::{fs, process};
-mod conversation_writer;
-mod converter;
-mod model;
-mod utils;
+use std::{fs, process, sync::Arc};
+mod logging;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+    /// Path to conversations.json, or to the ZIP archive of a data export
     #[arg(short, long)]
     input: PathBuf,
 
     #[arg(short, long)]
     output_folder: PathBuf,
+
+    /// Export every branch of edited or regenerated conversations, one file per branch
+    #[arg(long)]
+    all_branches: bool,
+
+    /// Export the reasoning of the assistant (o1/o3 thoughts, Claude extended thinking) as
+    /// collapsible sections preceding its answers
+    #[arg(long)]
+    reasoning: bool,
+
+    /// Export the system prompts and custom instructions in effect, at the top of ChatGPT
+    /// conversations
+    #[arg(long)]
+    system_messages: bool,
+
+    /// Source of the input file
+    #[arg(short, long, value_enum, default_value_t)]
+    source: InputFormat,
+
+    /// Formats of the exported files, repeated or comma-separated to export several at once
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "markdown"
+    )]
+    format: Vec<OutputFormat>,
+
+    /// Time zone of dates and times: an IANA name such as Europe/Paris, UTC or local
+    #[arg(long, default_value_t = Timezone::Utc)]
+    timezone: Timezone,
+
+    /// strftime pattern of the conversation dates in file names, headings and indexes
+    #[arg(long, default_value = DEFAULT_DATE_PATTERN)]
+    date_format: String,
+
+    /// Timestamp the date of each conversation is taken from
+    #[arg(long, value_enum, default_value_t)]
+    date_field: DateField,
+
+    /// Path pattern of the subfolders the files are written to, e.g. "{year}/{month}", with the
+    /// placeholders {year}, {month}, {day}, {gizmo} (custom GPT or project) and {source}
+    #[arg(long, default_value = "")]
+    layout: String,
+
+    /// Head Markdown files with a front matter block holding the metadata of the conversation
+    #[arg(long, value_enum)]
+    front_matter: Option<FrontMatter>,
+
+    /// Annotate each message with its local time and, for answers, the model that wrote it
+    #[arg(long)]
+    annotate: bool,
+
+    /// Precede the title and each message with an anchor named after its id in the export, to
+    /// link to a given answer with "file.md#<message id>"
+    #[arg(long)]
+    anchors: bool,
+
+    /// Template file laying out Markdown files, in Jinja syntax, instead of the built-in layout
+    #[arg(short, long)]
+    template: Option<PathBuf>,
+
+    /// Remove the files of conversations that are no longer in the export, or no longer match
+    /// the filters, unless the run has errors
+    #[arg(long)]
+    prune: bool,
+
+    /// Export conversations dated from this day on (YYYY-MM-DD), see --date-field
+    #[arg(long, value_parser = filter::parse_date)]
+    since: Option<chrono::NaiveDate>,
+
+    /// Export conversations dated up to this day included (YYYY-MM-DD), see --date-field
+    #[arg(long, value_parser = filter::parse_date)]
+    until: Option<chrono::NaiveDate>,
+
+    /// Export conversations whose title matches this regular expression, e.g. "(?i)invoice"
+    #[arg(long)]
+    title: Option<regex::Regex>,
+
+    /// Export conversations with a message containing this keyword, ignoring case; repeated to
+    /// accept any of several keywords
+    #[arg(long)]
+    contains: Vec<String>,
+
+    /// Export conversations in which the user asked at least this many questions
+    #[arg(long, default_value_t = 0)]
+    min_turns: usize,
+
+    /// Export conversations answered by this model, e.g. "gpt-4o"; repeated or comma-separated
+    /// to accept several
+    #[arg(long, value_delimiter = ',')]
+    model: Vec<String>,
+
+    /// Export conversations held with this custom GPT or project id; repeated or
+    /// comma-separated to accept several
+    #[arg(long, value_delimiter = ',')]
+    gizmo: Vec<String>,
+
+    /// Whether archived conversations are exported
+    #[arg(long, value_enum, default_value_t)]
+    archived: Archived,
+
+    /// Export only the conversations with these ids, repeated or comma-separated
+    #[arg(long, value_delimiter = ',')]
+    id: Vec<String>,
+
+    /// Leave out the conversations with these ids, repeated or comma-separated
+    #[arg(long, value_delimiter = ',')]
+    exclude_id: Vec<String>,
+
+    /// Log more details, repeated for more: -v for progress, -vv for the conversion and files of
+    /// each conversation (skipped messages, filters, written files), -vvv for everything
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less: -q for errors only, -qq for nothing
+    #[arg(short, long, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Format of the log messages written to the standard error output
+    #[arg(long, value_enum, default_value_t)]
+    log_format: LogFormat,
+}
+
+/// Builds the `Config` of the export specified by the command line.
+///
+/// # Errors
+///
+/// Returns an `Error` if the options are invalid, or if the template file cannot be read.
+fn config(cli: Cli) -> Result<Config, Error> {
+    let template = match &cli.template {
+        Some(path) => {
+            let source = fs::read_to_string(path).map_err(|e| Error::read(path, e))?;
+            let template = Template::new(source).map_err(|e| {
+                Error::Options(format!("Invalid template '{}': {}", path.display(), e))
+            })?;
+            Some(Arc::new(template))
+        }
+        None => None,
+    };
+    let date_format =
+        DateFormat::new(cli.timezone, &cli.date_format, cli.date_field).map_err(Error::Options)?;
+    let mut config = Config::new(cli.input, cli.output_folder);
+    config.source = cli.source;
+    config.formats = cli.format;
+    config.import_options.all_branches = cli.all_branches;
+    config.import_options.reasoning = cli.reasoning;
+    config.import_options.system_messages = cli.system_messages;
+    config.export_options.front_matter = cli.front_matter;
+    config.export_options.template = template;
+    config.export_options.date_format = date_format.clone();
+    config.export_options.annotate = cli.annotate;
+    config.export_options.anchors = cli.anchors;
+    config.layout = Layout::new(&cli.layout, date_format.clone()).map_err(Error::Options)?;
+    config.filter.since = cli.since;
+    config.filter.until = cli.until;
+    config.filter.date_format = date_format;
+    config.filter.title = cli.title;
+    config.filter.keywords = cli.contains;
+    config.filter.min_turns = cli.min_turns;
+    config.filter.models = cli.model;
+    config.filter.gizmos = cli.gizmo;
+    config.filter.archived = cli.archived;
+    config.filter.ids = cli.id;
+    config.filter.excluded_ids = cli.exclude_id;
+    config.prune = cli.prune;
+    Ok(config)
 }
 
-fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(&cli.input)
-        .map_err(|e| format!("Failed to read file '{}': {}", cli.input.display(), e))?;
-
-    let interactions: Vec<GPTInteraction> = serde_json::from_str(&content).map_err(|e| {
-        format!(
-            "Failed to parse JSON in file '{}': {}",
-            cli.input.display(),
-            e
-        )
-    })?;
-
-    conversation_writer::write(
-        interactions
-            .into_iter()
-            .map(converter::create_conversation_from)
-            .collect(),
-        cli.output_folder,
+/// Prints the files created, updated and removed by a run, followed by a summary mentioning the
+/// conversations left out by the filters, the pruning skipped because of errors and the errors,
+/// if any.
+fn report(report: &Report) {
+    let changes = &report.changes;
+    for (label, paths) in [
+        ("Created", &changes.created),
+        ("Updated", &changes.updated),
+        ("Removed", &changes.removed),
+        ("Disambiguated", &changes.disambiguated),
+    ] {
+        for path in paths {
+            println!("{}: {}", label, path);
+        }
+    }
+    println!(
+        "{} created, {} updated, {} unchanged, {} removed, {} disambiguated",
+        changes.created.len(),
+        changes.updated.len(),
+        changes.unchanged,
+        changes.removed.len(),
+        changes.disambiguated.len()
     );
-    Ok(())
+    if report.filtered > 0 {
+        println!("{} conversations left out by the filters", report.filtered);
+    }
+    if report.prune_skipped {
+        warn!("Skipped --prune because of the errors, no file was removed");
+    }
+    if let Some(summary) = error::summary(&report.errors) {
+        error!("Completed with {}", summary);
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
-    if let Err(e) = run(cli) {
-        eprintln!("Error: {}", e);
-        process::exit(1);
+    logging::init(logging::level(cli.verbose, cli.quiet), cli.log_format);
+    match config(cli).and_then(|config| herodote::export(&config)) {
+        Ok(run) => {
+            report(&run);
+            if let Some(e) = &run.stopped {
+                error!("{}", e);
+                process::exit(1);
+            }
+            // Part of the export could not be written.
+            if !run.errors.is_empty() {
+                process::exit(3);
+            }
+        }
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
     }
 }
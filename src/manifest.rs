@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::Path,
+};
+
+/// Name of the manifest file, stored in the output folder.
+pub const MANIFEST_FILE: &str = ".herodote-manifest.json";
+
+/// Record of the files written to an output folder by previous runs.
+///
+/// Each file produced for a conversation is recorded under its output path with the id and
+/// update time of the conversation and the hash of its content. On the next run, a file whose
+/// content hash did not change is not written again, which keeps the modification times of the
+/// archive stable and avoids churn in version-controlled output folders. Files of conversations
+/// that disappeared from the export can be removed with `prune`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    files: BTreeMap<String, ManifestEntry>,
+    /// Files produced during the current run, used by `prune`.
+    #[serde(skip)]
+    seen: HashSet<String>,
+}
+
+/// A file written for a conversation, as recorded in the `Manifest`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Identifier of the conversation, if the export provides one.
+    pub id: Option<String>,
+    /// Time of the last update of the conversation, in seconds since the Unix epoch, or 0 for
+    /// the indexes, which belong to no conversation.
+    #[serde(default)]
+    pub update_time: f64,
+    /// SHA-256 hash of the content of the file, in hexadecimal.
+    pub hash: String,
+}
+
+/// Outcome of a run, listing the files by path relative to the output folder.
+#[derive(Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Changes {
+    /// Files written for the first time.
+    pub created: Vec<String>,
+    /// Files whose content changed.
+    pub updated: Vec<String>,
+    /// Number of files whose content did not change.
+    pub unchanged: usize,
+    /// Files removed by `Manifest::prune`.
+    pub removed: Vec<String>,
+    /// Files given a suffix because several conversations would have been written to the same
+    /// file name.
+    pub disambiguated: Vec<String>,
+}
+
+impl Manifest {
+    /// Loads the manifest of an output folder.
+    ///
+    /// # Returns
+    ///
+    /// The recorded `Manifest`, or an empty one when the folder has no manifest yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest exists but cannot be read or parsed.
+    pub fn load(folder: &Path) -> io::Result<Manifest> {
+        let content = match fs::read_to_string(folder.join(MANIFEST_FILE)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Manifest::default()),
+            Err(err) => return Err(err),
+        };
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Saves the manifest in the output folder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be written.
+    pub fn save(&self, folder: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(folder.join(MANIFEST_FILE), content + "\n")
+    }
+
+    /// Returns the entry recorded for a file, if any.
+    pub fn get(&self, path: &str) -> Option<&ManifestEntry> {
+        self.files.get(path)
+    }
+
+    /// Records a file produced during the current run.
+    pub fn record(&mut self, path: String, entry: ManifestEntry) {
+        self.seen.insert(path.clone());
+        self.files.insert(path, entry);
+    }
+
+    /// Keeps the files recorded for the conversation `id` as if they were produced during the
+    /// current run, so that `prune` does not remove them. Used for the conversations that are
+    /// still in the export but could not be written.
+    pub fn keep(&mut self, id: &str) {
+        for (path, entry) in &self.files {
+            if entry.id.as_deref() == Some(id) {
+                self.seen.insert(path.clone());
+            }
+        }
+    }
+
+    /// Removes the files that were recorded by previous runs but not produced by this one,
+    /// typically because their conversation is no longer in the export.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the removed files, relative to the output folder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be removed. Files that are already missing are only
+    /// dropped from the manifest. Subfolders left empty are removed as well.
+    pub fn prune(&mut self, folder: &Path) -> io::Result<Vec<String>> {
+        let stale: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !self.seen.contains(*path))
+            .cloned()
+            .collect();
+        for path in &stale {
+            match fs::remove_file(folder.join(path)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {
+                    self.files.remove(path);
+                }
+            }
+            // Remove the subfolders left empty, stopping at the first one that is not.
+            for parent in Path::new(path).ancestors().skip(1) {
+                if parent.as_os_str().is_empty() || fs::remove_dir(folder.join(parent)).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(stale)
+    }
+}
+
+/// Returns the SHA-256 hash of `content`, in hexadecimal.
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(hash: &str) -> ManifestEntry {
+        ManifestEntry {
+            id: Some("c1".to_string()),
+            update_time: 1672531200.0,
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_manifest_save_load_and_prune() {
+        let folder = PathBuf::from("./test_manifest");
+        fs::create_dir_all(&folder).unwrap();
+        assert!(Manifest::load(&folder).unwrap().files.is_empty());
+
+        let mut manifest = Manifest::default();
+        manifest.record("kept.md".to_string(), entry("1"));
+        manifest.record("gone.md".to_string(), entry("2"));
+        manifest.save(&folder).unwrap();
+        fs::write(folder.join("kept.md"), "kept").unwrap();
+        fs::write(folder.join("gone.md"), "gone").unwrap();
+
+        let mut manifest = Manifest::load(&folder).unwrap();
+        assert_eq!(manifest.get("gone.md"), Some(&entry("2")));
+        manifest.record("kept.md".to_string(), entry("1"));
+
+        assert_eq!(manifest.prune(&folder).unwrap(), vec!["gone.md"]);
+        assert!(manifest.get("gone.md").is_none());
+        assert!(!folder.join("gone.md").exists());
+        assert!(folder.join("kept.md").exists());
+
+        // Clean up
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,484 @@
+use crate::{
+    date_format::DateFormat,
+    exporter::{ExportOptions, Exporter},
+    front_matter::front_matter,
+    html_renderer::escape_html,
+    model::{Citation, Conversation, ConversationItem, Image, ItemKind},
+};
+use log::warn;
+
+/// `Exporter` writing each conversation as a Markdown file, optionally headed by a front matter
+/// block holding the metadata of the conversation.
+///
+/// The body of the file is laid out by `conversation_to_md`, or by a user-defined `Template`.
+/// A conversation the template fails to render is logged as a warning and written with the
+/// built-in layout, so that it is not missing from the export.
+pub struct MarkdownExporter {
+    /// Options of the export.
+    pub options: ExportOptions,
+}
+
+impl Exporter for MarkdownExporter {
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn render(&self, conversation: &Conversation) -> Vec<u8> {
+        let mut content = self
+            .options
+            .front_matter
+            .map(|format| front_matter(conversation, format, &self.options.date_format))
+            .unwrap_or_default();
+        let body = self.options.template.as_ref().and_then(|template| {
+            template
+                .render(conversation, &self.options.date_format)
+                .map_err(|e| {
+                    warn!(
+                        "Failed to render template for conversation '{}': {}",
+                        conversation.title, e
+                    )
+                })
+                .ok()
+        });
+        content.push_str(&body.unwrap_or_else(|| conversation_to_md(conversation, &self.options)));
+        content.into_bytes()
+    }
+}
+
+/// Converts a `Conversation` object into a Markdown formatted string.
+///
+/// This function takes a `Conversation` struct, iterates over its items, and constructs
+/// a Markdown representation of the conversation. Each item in the conversation is prefixed
+/// with a section title based on the author of the message, distinguishing between user
+/// input and system responses.
+///
+/// # Arguments
+///
+/// * `conversation` - A reference to a `Conversation` object that contains a title and a collection
+///   of conversation items, each with an author and text content.
+///
+/// * `options` - The `ExportOptions` of the export. With `annotate`, each section starts with the
+///   time of its message and, for answers, the model that wrote it (see `item_annotation`).
+///
+/// # Returns
+///
+/// A `String` containing the entire conversation formatted as Markdown. The output begins with an
+/// H1 title derived from the conversation's title, followed by each item formatted as an H2 section
+/// (see `section_title` and `item_to_md`), except reasoning, which is folded in a `<details>` block
+/// summarized by its section title. When the conversation is one of several branches, a line
+/// stating its position (e.g. "Branch 2 of 3") follows the title. With the `anchors` option, the
+/// title and each section are preceded by an `<a id="...">` anchor named after the id of the
+/// conversation or message in the export (see `anchor`).
+///
+/// # Example
+///
+/// ```
+/// use herodote::exporter::ExportOptions;
+/// use herodote::markdown_renderer::conversation_to_md;
+/// use herodote::model::{Conversation, ConversationItem};
+///
+/// let conversation = Conversation::new(
+///     String::from("Sample Conversation"),
+///     vec![
+///         ConversationItem::new("What is the weather today?".into(), "user".into(), 0.0),
+///         ConversationItem::new("It is sunny today.".into(), "assistant".into(), 0.0),
+///     ],
+///     String::from("2023-01-01"),
+/// );
+/// let markdown = conversation_to_md(&conversation, &ExportOptions::default());
+/// assert_eq!(
+///     markdown,
+///     "# Sample Conversation\n\n## Question\nWhat is the weather today?\n\n## Answer\n\
+///      It is sunny today.\n\n"
+/// );
+/// ```
+pub fn conversation_to_md(conversation: &Conversation, options: &ExportOptions) -> String {
+    let mut content = String::new();
+    if options.anchors {
+        content.push_str(&anchor(conversation.id.as_deref()));
+    }
+    content.push_str(&format!("# {}\n\n", conversation.title));
+    if let Some(branch) = conversation.branch {
+        content.push_str(&format!(
+            "*Branch {} of {}*\n\n",
+            branch.index, branch.count
+        ));
+    }
+
+    for item in &conversation.items {
+        if options.anchors {
+            content.push_str(&anchor(item.id.as_deref()));
+        }
+        let reasoning = matches!(item.kind, ItemKind::Reasoning { .. });
+        if reasoning {
+            content.push_str(&format!(
+                "<details>\n<summary>{}</summary>\n\n",
+                escape_html(&section_title(item))
+            ));
+        } else {
+            content.push_str(&format!("## {}\n", section_title(item)));
+        }
+        if let Some(annotation) = options
+            .annotate
+            .then(|| item_annotation(item, &options.date_format))
+            .flatten()
+        {
+            content.push_str(&format!("*{}*\n\n", annotation));
+        }
+        content.push_str(&format!("{}\n\n", item_to_md(item)));
+        if reasoning {
+            content.push_str("</details>\n\n");
+        }
+    }
+    content
+}
+
+/// Returns the annotation of a `ConversationItem`: the time of its message in the time zone of
+/// `date_format`, followed for answers by the model that wrote it, e.g.
+/// "2023-01-01 10:30 · gpt-4o".
+///
+/// # Returns
+///
+/// `None` when the export records neither the time nor the model of the message.
+pub fn item_annotation(item: &ConversationItem, date_format: &DateFormat) -> Option<String> {
+    let mut parts = Vec::new();
+    if item.time > 0.0 {
+        parts.push(date_format.time(item.time));
+    }
+    if let Some(model) = &item.model {
+        parts.push(model.clone());
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Returns the title of the section of a `ConversationItem`.
+///
+/// Text items authored by "user" are labeled "Question" and other text items are labeled
+/// "Answer". Code and execution output items are labeled "Code" and "Output", followed by the
+/// name of the tool in parentheses, e.g. "Output (python)". Reasoning is labeled by its recap,
+/// e.g. "Thought for 12 seconds", or "Reasoning" without one. System prompts and custom
+/// instructions are labeled "System prompt" and "Custom instructions".
+pub fn section_title(item: &ConversationItem) -> String {
+    match &item.kind {
+        ItemKind::Text if item.author == "user" => "Question".to_string(),
+        ItemKind::Text => "Answer".to_string(),
+        ItemKind::Code { .. } => format!("Code ({})", item.author),
+        ItemKind::ExecutionOutput => format!("Output ({})", item.author),
+        ItemKind::Reasoning { recap } => recap.clone().unwrap_or_else(|| "Reasoning".to_string()),
+        ItemKind::SystemPrompt => "System prompt".to_string(),
+        ItemKind::CustomInstructions => "Custom instructions".to_string(),
+    }
+}
+
+/// Returns the body of a `ConversationItem` as Markdown.
+///
+/// Text and reasoning items are already Markdown and are returned as is. Code is wrapped in a
+/// fenced code block tagged with its language, and execution output in an untagged fenced block.
+/// System prompts and custom instructions are quoted, to set them apart from the conversation.
+/// Images follow the text, each on its own paragraph (see `image_to_md`), then the footnotes of the
+/// sources cited by the text (see `citation_to_md`).
+pub fn item_to_md(item: &ConversationItem) -> String {
+    let mut blocks = Vec::new();
+    match &item.kind {
+        ItemKind::Text | ItemKind::Reasoning { .. } if item.text.trim().is_empty() => {}
+        ItemKind::Text | ItemKind::Reasoning { .. } => blocks.push(item.text.clone()),
+        ItemKind::Code { language } => {
+            blocks.push(fenced(&item.text, language.as_deref().unwrap_or("")))
+        }
+        ItemKind::ExecutionOutput => blocks.push(fenced(&item.text, "")),
+        ItemKind::SystemPrompt | ItemKind::CustomInstructions => blocks.push(quoted(&item.text)),
+    }
+    blocks.extend(item.images.iter().map(image_to_md));
+    if !item.citations.is_empty() {
+        let footnotes: Vec<String> = item.citations.iter().map(citation_to_md).collect();
+        blocks.push(footnotes.join("\n"));
+    }
+    blocks.join("\n\n")
+}
+
+/// Returns the footnote of a `Citation`, linking to the cited page by its title, e.g.
+/// `[^1]: [Rust Blog](<https://blog.rust-lang.org/>)`, or by its URL when it has no title.
+fn citation_to_md(citation: &Citation) -> String {
+    match &citation.title {
+        Some(title) => format!(
+            "[^{}]: [{}](<{}>)",
+            citation.number,
+            title.replace('[', "\\[").replace(']', "\\]"),
+            citation.url
+        ),
+        None => format!("[^{}]: <{}>", citation.number, citation.url),
+    }
+}
+
+/// Returns a Markdown image linking to the copy of an `Image` in the output folder, or a
+/// visible placeholder naming the missing file when the image was not found in the export.
+fn image_to_md(image: &Image) -> String {
+    match &image.path {
+        Some(path) => format!("![image](<{}>)", path),
+        None => format!("*[Image not found in export: {}]*", image.file_id),
+    }
+}
+
+/// Returns the line anchoring the heading that follows it, e.g. `<a id="abc-123"></a>`, so that
+/// links ending in `#abc-123` point to it, or an empty string without `id`.
+///
+/// The anchor is inline HTML rather than a heading attribute, which most Markdown renderers
+/// (GitHub, Obsidian, static-site generators) keep as is.
+pub fn anchor(id: Option<&str>) -> String {
+    id.map(|id| format!("<a id=\"{}\"></a>\n", escape_html(id)))
+        .unwrap_or_default()
+}
+
+/// Quotes every line of `text` as a Markdown blockquote.
+fn quoted(text: &str) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| match line {
+            "" => ">".to_string(),
+            line => format!("> {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wraps `text` in a fenced code block, using a fence longer than any backtick run of the text
+/// so that the block cannot be closed early.
+fn fenced(text: &str, info: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    format!(
+        "{}{}\n{}\n{}",
+        fence,
+        info,
+        text.trim_end_matches('\n'),
+        fence
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Conversation, ConversationItem};
+
+    #[test]
+    fn test_conversation_to_md() {
+        let conversation = Conversation {
+            title: "Test Conversation".to_string(),
+            date: "2023-01-01".to_string(),
+            items: vec![
+                ConversationItem {
+                    id: None,
+                    text: "Hello!".to_string(),
+                    author: "user".to_string(),
+                    time: 1672531200.0,
+                    kind: ItemKind::Text,
+                    images: Vec::new(),
+                    model: None,
+                    citations: Vec::new(),
+                },
+                ConversationItem {
+                    id: None,
+                    text: "Hi!".to_string(),
+                    author: "assistant".to_string(),
+                    time: 1672531210.0,
+                    kind: ItemKind::Text,
+                    images: Vec::new(),
+                    model: None,
+                    citations: Vec::new(),
+                },
+            ],
+            branch: None,
+            id: None,
+            create_time: 0.0,
+            update_time: 0.0,
+            model: None,
+            gizmo_id: None,
+            source: None,
+            archived: false,
+        };
+
+        let markdown = conversation_to_md(&conversation, &ExportOptions::default());
+        let expected = r#"# Test Conversation
+
+## Question
+Hello!
+
+## Answer
+Hi!
+
+"#;
+        assert_eq!(markdown, expected);
+    }
+
+    #[test]
+    fn test_conversation_to_md_code_interpreter() {
+        let mut code =
+            ConversationItem::new("print(\"```\")".to_string(), "python".to_string(), 1.0);
+        code.kind = ItemKind::Code {
+            language: Some("python".to_string()),
+        };
+        let mut output = ConversationItem::new("```\n".to_string(), "python".to_string(), 2.0);
+        output.kind = ItemKind::ExecutionOutput;
+        let conversation = Conversation::new(
+            "Analysis".to_string(),
+            vec![code, output],
+            "2023-01-01".to_string(),
+        );
+
+        let markdown = conversation_to_md(&conversation, &ExportOptions::default());
+        let expected = r#"# Analysis
+
+## Code (python)
+````python
+print("```")
+````
+
+## Output (python)
+````
+```
+````
+
+"#;
+        assert_eq!(markdown, expected);
+    }
+
+    #[test]
+    fn test_conversation_to_md_annotated() {
+        let question =
+            ConversationItem::new("Hello!".to_string(), "user".to_string(), 1672531200.0);
+        let mut answer = ConversationItem::new("Hi!".to_string(), "assistant".to_string(), 0.0);
+        answer.model = Some("gpt-4o".to_string());
+        let conversation = Conversation::new(
+            "Test Conversation".to_string(),
+            vec![question, answer],
+            "2023-01-01".to_string(),
+        );
+        let options = ExportOptions {
+            annotate: true,
+            ..ExportOptions::default()
+        };
+
+        let markdown = conversation_to_md(&conversation, &options);
+        let expected = format!(
+            "# Test Conversation\n\n## Question\n*{}*\n\nHello!\n\n## Answer\n*gpt-4o*\n\nHi!\n\n",
+            DateFormat::default().time(1672531200.0)
+        );
+        assert_eq!(markdown, expected);
+    }
+
+    #[test]
+    fn test_conversation_to_md_anchors() {
+        let mut question = ConversationItem::new("Hello!".to_string(), "user".to_string(), 0.0);
+        question.id = Some("m1".to_string());
+        let answer = ConversationItem::new("Hi!".to_string(), "assistant".to_string(), 0.0);
+        let mut conversation = Conversation::new(
+            "Test Conversation".to_string(),
+            vec![question, answer],
+            "2023-01-01".to_string(),
+        );
+        conversation.id = Some("c\"1".to_string());
+        let options = ExportOptions {
+            anchors: true,
+            ..ExportOptions::default()
+        };
+
+        assert_eq!(
+            conversation_to_md(&conversation, &options),
+            "<a id=\"c&quot;1\"></a>\n# Test Conversation\n\n<a id=\"m1\"></a>\n## Question\nHello!\n\n## Answer\nHi!\n\n"
+        );
+    }
+
+    #[test]
+    fn test_item_to_md_images() {
+        let mut item = ConversationItem::new("Look:".to_string(), "user".to_string(), 0.0);
+        item.images = vec![
+            Image {
+                file_id: "file-abc".to_string(),
+                path: Some("assets/file-abc-my photo.png".to_string()),
+            },
+            Image::new("file-def".to_string()),
+        ];
+
+        assert_eq!(
+            item_to_md(&item),
+            "Look:\n\n![image](<assets/file-abc-my photo.png>)\n\n*[Image not found in export: file-def]*"
+        );
+
+        item.text = String::new();
+        item.images.truncate(1);
+        assert_eq!(
+            item_to_md(&item),
+            "![image](<assets/file-abc-my photo.png>)"
+        );
+    }
+
+    #[test]
+    fn test_item_to_md_citations() {
+        let mut item = ConversationItem::new(
+            "Rust 1.0 was released in 2015[^3][^4].".to_string(),
+            "assistant".to_string(),
+            0.0,
+        );
+        item.citations = vec![
+            Citation {
+                number: 3,
+                title: Some("Announcing [Rust] 1.0".to_string()),
+                url: "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html".to_string(),
+            },
+            Citation {
+                number: 4,
+                title: None,
+                url: "https://example.com/".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            item_to_md(&item),
+            "Rust 1.0 was released in 2015[^3][^4].\n\n\
+             [^3]: [Announcing \\[Rust\\] 1.0](<https://blog.rust-lang.org/2015/05/15/Rust-1.0.html>)\n\
+             [^4]: <https://example.com/>"
+        );
+    }
+
+    #[test]
+    fn test_conversation_to_md_reasoning() {
+        let mut reasoning = ConversationItem::new(
+            "**Recalling** the <b>date</b>".to_string(),
+            "assistant".to_string(),
+            0.0,
+        );
+        reasoning.kind = ItemKind::Reasoning {
+            recap: Some("Thought for 5 seconds".to_string()),
+        };
+        let conversation = Conversation::new(
+            "Rust".to_string(),
+            vec![
+                reasoning,
+                ConversationItem::new("In 2015.".to_string(), "assistant".to_string(), 0.0),
+            ],
+            "2023-01-01".to_string(),
+        );
+
+        assert_eq!(
+            conversation_to_md(&conversation, &ExportOptions::default()),
+            "# Rust\n\n<details>\n<summary>Thought for 5 seconds</summary>\n\n\
+             **Recalling** the <b>date</b>\n\n</details>\n\n## Answer\nIn 2015.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_item_to_md_custom_instructions() {
+        let mut item = ConversationItem::new(
+            "User profile:\n```I write Rust.```\n\nBe concise.\n".to_string(),
+            "system".to_string(),
+            0.0,
+        );
+        item.kind = ItemKind::CustomInstructions;
+
+        assert_eq!(section_title(&item), "Custom instructions");
+        assert_eq!(
+            item_to_md(&item),
+            "> User profile:\n> ```I write Rust.```\n>\n> Be concise."
+        );
+    }
+}
@@ -1,30 +1,181 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
+/// A conversation of the target model, rendered by the exporters whatever the tool it comes from.
+///
+/// A `Conversation` is created with `Conversation::new`; the fields the export provides are set
+/// afterwards.
+#[derive(Debug)]
+#[non_exhaustive]
 pub struct Conversation {
+    /// Title of the conversation, as shown in the application it was exported from.
     pub title: String,
+    /// Messages of the conversation, in display order.
     pub items: Vec<ConversationItem>,
+    /// Date of the conversation as written in file names, headings and indexes.
     pub date: String,
+    /// Position of the conversation among the branches of the same conversation, when every
+    /// branch is exported.
+    pub branch: Option<Branch>,
+    /// Identifier of the conversation in the export, when the export provides one.
+    pub id: Option<String>,
+    /// Time of the creation of the conversation, in seconds since the Unix epoch.
+    pub create_time: f64,
+    /// Time of the last update of the conversation, in seconds since the Unix epoch.
+    pub update_time: f64,
+    /// Slug of the model that answered, e.g. "gpt-4o", when the export records it.
+    pub model: Option<String>,
+    /// Identifier of the custom GPT the conversation was held with, if any.
+    pub gizmo_id: Option<String>,
+    /// Name of the `Importer` the conversation comes from, e.g. "chatgpt".
+    pub source: Option<&'static str>,
+    /// Whether the conversation was archived in the application it was exported from.
+    pub archived: bool,
 }
 
 impl Conversation {
+    /// Creates a `Conversation` with a title, items and date, and no other metadata.
     pub fn new(title: String, items: Vec<ConversationItem>, date: String) -> Conversation {
-        Conversation { title, items, date }
+        Conversation {
+            title,
+            items,
+            date,
+            branch: None,
+            id: None,
+            create_time: 0.0,
+            update_time: 0.0,
+            model: None,
+            gizmo_id: None,
+            source: None,
+            archived: false,
+        }
     }
 }
 
+/// Position of a conversation thread among the alternative branches of the same conversation.
+///
+/// Branches are numbered from 1, in the order their leaves are reached when walking the
+/// conversation tree depth-first along `Node.children`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Branch {
+    /// Number of the branch, from 1.
+    pub index: usize,
+    /// Number of branches of the conversation.
+    pub count: usize,
+}
+
+impl Branch {
+    /// Creates the `Branch` numbered `index` among `count` branches.
+    pub fn new(index: usize, count: usize) -> Branch {
+        Branch { index, count }
+    }
+}
+
+/// A message of a `Conversation`, such as a question, an answer or the output of a tool.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct ConversationItem {
+    /// Identifier of the message in the export, when the export provides one.
+    pub id: Option<String>,
+    /// Text of the message, in Markdown for items of kind `ItemKind::Text`.
     pub text: String,
+    /// Role of the author of the message, "user" or "assistant", or the name of a tool.
     pub author: String,
+    /// Time the message was written, in seconds since the Unix epoch, or 0 if unknown.
     pub time: f64,
+    /// Nature of the text of the message.
+    pub kind: ItemKind,
+    /// Images attached to the message.
+    pub images: Vec<Image>,
+    /// Slug of the model that wrote an assistant message, when the export records it.
+    pub model: Option<String>,
+    /// Sources cited by the text, which refers to them with Markdown footnote references such
+    /// as `[^1]`.
+    pub citations: Vec<Citation>,
 }
 
 impl ConversationItem {
+    /// Creates an item of kind `ItemKind::Text`, without id, images, model or citations.
     pub fn new(text: String, author: String, time: f64) -> ConversationItem {
-        ConversationItem { text, author, time }
+        ConversationItem {
+            id: None,
+            text,
+            author,
+            time,
+            kind: ItemKind::Text,
+            images: Vec::new(),
+            model: None,
+            citations: Vec::new(),
+        }
     }
 }
 
+/// A web page cited by a `ConversationItem`, such as a search result of a browsing answer.
+///
+/// `number` is the label of the footnote referring to the page, unique within the conversation
+/// so that the footnotes of several items can be rendered in the same document.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Citation {
+    /// Label of the footnote referring to the page.
+    pub number: usize,
+    /// Title of the page, if the export records one.
+    pub title: Option<String>,
+    /// URL of the page.
+    pub url: String,
+}
+
+/// An image attached to a `ConversationItem`, such as an uploaded picture or a generated one.
+///
+/// The image is identified by the id of its file in the export (e.g. `file-AbC123`). `path`
+/// is set once the file has been found among the media of the export, and holds the path of
+/// its copy relative to the output folder. It stays `None` when the file is missing.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Image {
+    /// Id of the file of the image in the export.
+    pub file_id: String,
+    /// Path of the copy of the image, relative to the output folder, once the file was found.
+    pub path: Option<String>,
+}
+
+impl Image {
+    /// Creates an `Image` for the file `file_id`, not found yet.
+    pub fn new(file_id: String) -> Image {
+        Image {
+            file_id,
+            path: None,
+        }
+    }
+}
+
+/// Nature of the text of a `ConversationItem`.
+///
+/// Items produced by tools hold raw code or program output rather than Markdown, and are
+/// attributed to the tool (e.g. "python") through the `author` of the item.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ItemKind {
+    /// Markdown text written by the user or the assistant.
+    #[default]
+    Text,
+    /// Code sent by the assistant to a tool for execution, in the given language if known.
+    Code {
+        /// Language of the code, e.g. "python".
+        language: Option<String>,
+    },
+    /// Output of the execution of code by a tool.
+    ExecutionOutput,
+    /// Reasoning of the assistant before its answer, in Markdown, with the recap of the export
+    /// if any, e.g. "Thought for 12 seconds".
+    Reasoning {
+        /// Recap of the reasoning, e.g. "Thought for 12 seconds".
+        recap: Option<String>,
+    },
+    /// Instructions given to the assistant by the system rather than in the conversation.
+    SystemPrompt,
+    /// Custom instructions of the user, given to the assistant at the start of every
+    /// conversation.
+    CustomInstructions,
+}
+
 /// Represents an interaction with a Generative Pre-trained Transformer (GPT) model.
 ///
 /// This struct is used to store details about a specific interaction, including its
@@ -33,8 +184,11 @@ impl ConversationItem {
 ///
 /// # Fields
 ///
-/// * `title` - A `String` representing the title of the interaction, which can be used to identify or
-///   describe the conversation's context or subject.
+/// * `id` / `conversation_id` - Optional `String`s identifying the conversation. Exports usually
+///   carry both, with the same value.
+///
+/// * `title` - A `String` representing the title of the interaction, which can be used to identify
+///   or describe the conversation's context or subject.
 ///
 /// * `create_time` - A `f64` value representing the timestamp when the interaction was created.
 ///   This is typically expressed in seconds since the Unix epoch.
@@ -47,6 +201,19 @@ impl ConversationItem {
 ///   that encapsulates the specifics of that part of the conversation, including its message,
 ///   parent, and children relationships.
 ///
+/// * `current_node` - An optional `String` holding the identifier of the node the user last
+///   interacted with. Following the `parent` links from this node up to the root yields the
+///   thread that was displayed when the conversation was exported, leaving out abandoned
+///   branches created by edited prompts or regenerated answers.
+///
+/// * `default_model_slug` - An optional `String` naming the model selected when the
+///   conversation was started, such as "gpt-4o".
+///
+/// * `gizmo_id` - An optional `String` identifying the custom GPT the conversation was held
+///   with, absent for conversations with ChatGPT itself.
+///
+/// * `is_archived` - An optional `bool` set when the user archived the conversation.
+///
 /// # Related Structures
 ///
 /// * `Node` - Represents a single node in the conversation, which may hold a message, have a parent
@@ -56,13 +223,20 @@ impl ConversationItem {
 ///   content, and various metadata fields.
 ///
 /// * `Author`, `Content`, `Part`, and `MessageMetadata` - Supporting structs used to further define
-///   the specifics of each message, such as content parts, author information, and additional metadata.
+///   the specifics of each message, such as content parts, author information, and additional
+///   metadata.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct GPTInteraction {
+pub(crate) struct GPTInteraction {
+    pub id: Option<String>,
+    pub conversation_id: Option<String>,
     pub title: String,
     pub create_time: f64,
     pub update_time: f64,
     pub mapping: HashMap<String, Node>,
+    pub current_node: Option<String>,
+    pub default_model_slug: Option<String>,
+    pub gizmo_id: Option<String>,
+    pub is_archived: Option<bool>,
 }
 
 impl GPTInteraction {}
@@ -76,7 +250,7 @@ impl GPTInteraction {}
 /// or any hierarchical data representation where nodes have unique identifiers and
 /// can optionally hold content (in the form of `Message`).
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Node {
+pub(crate) struct Node {
     /// A unique identifier for the node.
     pub id: String,
     /// An optional `Message` associated with the node.
@@ -144,7 +318,7 @@ pub struct Node {
 ///   which the message was sent or received, allowing differentiation between platforms
 ///   or mediums in multi-channel scenarios.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Message {
+pub(crate) struct Message {
     pub id: String,
     pub author: Author,
     pub create_time: Option<f64>,
@@ -175,7 +349,7 @@ pub struct Message {
 ///   the author, allowing for flexibility in storing metadata such as identifiers,
 ///   permissions, or other relevant attributes.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Author {
+pub(crate) struct Author {
     pub role: String,
     pub name: Option<String>,
     pub metadata: HashMap<String, String>,
@@ -193,10 +367,42 @@ pub struct Author {
 ///
 /// * `parts` - An optional `Vec<Part>` representing the different parts of the content.
 ///   Each part may encapsulate a string or a serialized object
+///
+/// * `text` - An optional `String` holding the content of messages that are not split into
+///   parts, such as the "code" sent to the code interpreter or its "execution_output".
+///
+/// * `language` - An optional `String` naming the language of "code" content. It is often
+///   "unknown", in which case the language is implied by the recipient of the message.
+///
+/// * `thoughts` - An optional `Vec<Thought>` holding the reasoning of models such as o1 and o3,
+///   in "thoughts" content.
+///
+/// * `content` - An optional `String` holding the recap of that reasoning in
+///   "reasoning_recap" content, e.g. "Thought for 12 seconds".
+///
+/// * `user_profile` and `user_instructions` - Optional `String`s holding the custom instructions
+///   of the user in "user_editable_context" content: what ChatGPT should know about the user,
+///   and how it should respond.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Content {
+pub(crate) struct Content {
     pub content_type: String,
     pub parts: Option<Vec<Part>>,
+    pub text: Option<String>,
+    pub language: Option<String>,
+    pub thoughts: Option<Vec<Thought>>,
+    pub content: Option<String>,
+    pub user_profile: Option<String>,
+    pub user_instructions: Option<String>,
+}
+
+/// A step of the reasoning of a model, in "thoughts" content: a short `summary` heading the
+/// Markdown `content` of the step.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Thought {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub summary: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: String,
 }
 
 /// Represents a part of the content within a message.
@@ -212,7 +418,7 @@ pub struct Content {
 ///   structured and complex data forms to be part of the message content.
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(untagged)]
-pub enum Part {
+pub(crate) enum Part {
     String(String),
     Object(serde_json::Value),
 }
@@ -229,7 +435,104 @@ pub enum Part {
 ///   key-value pairs of additional metadata, where each value can be a complex JSON
 ///   structure.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct MessageMetadata {
+pub(crate) struct MessageMetadata {
     #[serde(flatten)]
     pub additional_metadata: HashMap<String, serde_json::Value>,
 }
+
+/// Represents a conversation of a Claude.ai data export (`conversations.json`).
+///
+/// Unlike a `GPTInteraction`, a Claude conversation is a flat list of messages in display
+/// order. Timestamps are RFC 3339 strings rather than epoch seconds.
+///
+/// # Fields
+///
+/// * `uuid` - A `String` that uniquely identifies the conversation.
+///
+/// * `name` - A `String` holding the title of the conversation, which may be empty.
+///
+/// * `created_at` / `updated_at` - RFC 3339 timestamps of the creation and last update of the
+///   conversation.
+///
+/// * `chat_messages` - A `Vec<ClaudeMessage>` holding the messages of the conversation.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ClaudeConversation {
+    pub uuid: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub chat_messages: Vec<ClaudeMessage>,
+}
+
+/// Represents a message within a `ClaudeConversation`.
+///
+/// # Fields
+///
+/// * `uuid` - A `String` that uniquely identifies the message.
+///
+/// * `text` - A `String` holding the plain text of the message. Recent exports also split the
+///   message into `content` blocks, which take precedence when present.
+///
+/// * `content` - A `Vec<ClaudeContent>` holding the typed blocks of the message.
+///
+/// * `sender` - A `String` describing the author of the message, either "human" or "assistant".
+///
+/// * `created_at` - An RFC 3339 timestamp of the creation of the message.
+///
+/// * `attachments` - A `Vec<ClaudeAttachment>` of documents whose text was extracted and sent
+///   along with the message.
+///
+/// * `files` - A `Vec<ClaudeFile>` of files, such as images, uploaded with the message.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ClaudeMessage {
+    pub uuid: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub text: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: Vec<ClaudeContent>,
+    pub sender: String,
+    pub created_at: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub attachments: Vec<ClaudeAttachment>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub files: Vec<ClaudeFile>,
+}
+
+/// A typed block of a `ClaudeMessage`, such as "text", "thinking" or "tool_use".
+///
+/// Only the text of "text" blocks and the reasoning of "thinking" blocks (extended thinking)
+/// are used; other fields of the block are ignored.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ClaudeContent {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub text: Option<String>,
+    pub thinking: Option<String>,
+}
+
+/// A document attached to a `ClaudeMessage`, along with its extracted text.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ClaudeAttachment {
+    pub file_name: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub extracted_content: String,
+}
+
+/// A file uploaded with a `ClaudeMessage`.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ClaudeFile {
+    pub file_name: String,
+}
+
+/// Deserializes an explicit `null` as the default value of the field, as if it were missing.
+///
+/// Exports write `null` for some fields that are usually strings or lists, such as the name of a
+/// Claude conversation that was never named.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,192 @@
+use crate::{
+    date_format::DateFormat,
+    markdown_renderer::{item_to_md, section_title},
+    model::{Branch, Citation, Conversation, ConversationItem, Image, ItemKind},
+};
+use minijinja::Environment;
+use serde::Serialize;
+
+/// Name under which the user template is registered in the environment.
+const TEMPLATE_NAME: &str = "conversation";
+
+/// A user-defined template rendering conversations, written in the Jinja syntax of `minijinja`.
+///
+/// The template receives a single `conversation` variable, holding the metadata of the
+/// conversation and its `items`. See `ConversationContext` and `ItemContext` for the available
+/// fields. Blocks are trimmed (the newline following a `{% ... %}` tag is removed) and the
+/// trailing newline of the template is kept, so that templates read like the files they produce.
+#[derive(Debug)]
+pub struct Template {
+    environment: Environment<'static>,
+}
+
+/// The `conversation` variable of a template.
+#[derive(Serialize)]
+struct ConversationContext<'a> {
+    title: &'a str,
+    /// Date of the conversation used in file names, `YYYY-MM-DD` by default.
+    date: &'a str,
+    id: Option<&'a str>,
+    /// Creation and update times, RFC 3339 in the time zone of the export, or `none` when
+    /// unknown.
+    created: Option<String>,
+    updated: Option<String>,
+    model: Option<&'a str>,
+    gizmo_id: Option<&'a str>,
+    branch: Option<Branch>,
+    items: Vec<ItemContext<'a>>,
+}
+
+/// An element of `conversation.items` in a template.
+#[derive(Serialize)]
+struct ItemContext<'a> {
+    /// Id of the message in the export, or `none` when unknown.
+    id: Option<&'a str>,
+    /// "user", "assistant", or the name of the tool for code and execution output.
+    author: &'a str,
+    /// Raw text of the item, without the fences of code and output.
+    text: &'a str,
+    /// "text", "code", "execution_output", "reasoning", "system_prompt" or
+    /// "custom_instructions".
+    kind: &'static str,
+    /// Language of code items.
+    language: Option<&'a str>,
+    /// Time of the message, RFC 3339 in the time zone of the export, or `none` when unknown.
+    time: Option<String>,
+    /// Title of the section of the item in the built-in Markdown output, e.g. "Question".
+    section_title: String,
+    /// Body of the item in the built-in Markdown output, with fenced code and images.
+    markdown: String,
+    images: &'a [Image],
+    /// Sources cited by an answer, each with its footnote `number`, `title` and `url`.
+    citations: &'a [Citation],
+    /// Slug of the model that wrote an answer, or `none` when unknown.
+    model: Option<&'a str>,
+}
+
+impl Template {
+    /// Compiles a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `minijinja::Error` locating the syntax error if the template is invalid.
+    pub fn new(source: String) -> Result<Template, minijinja::Error> {
+        let mut environment = Environment::new();
+        environment.set_trim_blocks(true);
+        environment.set_keep_trailing_newline(true);
+        environment.add_template_owned(TEMPLATE_NAME, source)?;
+        Ok(Template { environment })
+    }
+
+    /// Renders a `Conversation` with the template.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `minijinja::Error` if the template fails at render time, for example when it
+    /// calls an unknown filter or iterates over a value that is not a sequence.
+    pub fn render(
+        &self,
+        conversation: &Conversation,
+        date_format: &DateFormat,
+    ) -> Result<String, minijinja::Error> {
+        let template = self.environment.get_template(TEMPLATE_NAME)?;
+        template.render(minijinja::context! {
+            conversation => conversation_context(conversation, date_format),
+        })
+    }
+}
+
+fn conversation_context<'a>(
+    conversation: &'a Conversation,
+    date_format: &DateFormat,
+) -> ConversationContext<'a> {
+    let time = |time: f64| (time > 0.0).then(|| date_format.datetime(time));
+    ConversationContext {
+        title: &conversation.title,
+        date: &conversation.date,
+        id: conversation.id.as_deref(),
+        created: time(conversation.create_time),
+        updated: time(conversation.update_time),
+        model: conversation.model.as_deref(),
+        gizmo_id: conversation.gizmo_id.as_deref(),
+        branch: conversation.branch,
+        items: conversation
+            .items
+            .iter()
+            .map(|item| item_context(item, time(item.time)))
+            .collect(),
+    }
+}
+
+fn item_context(item: &ConversationItem, time: Option<String>) -> ItemContext<'_> {
+    let (kind, language) = match &item.kind {
+        ItemKind::Text => ("text", None),
+        ItemKind::Code { language } => ("code", language.as_deref()),
+        ItemKind::ExecutionOutput => ("execution_output", None),
+        ItemKind::Reasoning { .. } => ("reasoning", None),
+        ItemKind::SystemPrompt => ("system_prompt", None),
+        ItemKind::CustomInstructions => ("custom_instructions", None),
+    };
+    ItemContext {
+        id: item.id.as_deref(),
+        author: &item.author,
+        text: &item.text,
+        kind,
+        language,
+        time,
+        section_title: section_title(item),
+        markdown: item_to_md(item),
+        images: &item.images,
+        citations: &item.citations,
+        model: item.model.as_deref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_render() {
+        let template = Template::new(
+            r#"# {{ conversation.title }} ({{ conversation.model or "unknown model" }})
+{% for item in conversation.items %}
+{% if item.author == "user" %}
+> [!question] {{ item.time }}
+> {{ item.text }}
+{% else %}
+**{{ item.author }}**: {{ item.markdown }}
+{% endif %}
+{% endfor %}
+"#
+            .to_string(),
+        )
+        .unwrap();
+        let mut conversation = Conversation::new(
+            "Test Conversation".to_string(),
+            vec![
+                ConversationItem::new("Hello!".to_string(), "user".to_string(), 1672531200.0),
+                ConversationItem::new("Hi!".to_string(), "assistant".to_string(), 0.0),
+            ],
+            "2023-01-01".to_string(),
+        );
+        conversation.model = Some("gpt-4o".to_string());
+
+        assert_eq!(
+            template.render(&conversation, &DateFormat::default()).unwrap(),
+            "# Test Conversation (gpt-4o)\n> [!question] 2023-01-01T00:00:00Z\n> Hello!\n**assistant**: Hi!\n"
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(Template::new("{% for item in %}".to_string()).is_err());
+
+        let template = Template::new("{{ conversation.title | unknown_filter }}".to_string());
+        let conversation = Conversation::new("Title".to_string(), Vec::new(), String::new());
+        assert!(template
+            .unwrap()
+            .render(&conversation, &DateFormat::default())
+            .is_err());
+    }
+}
@@ -9,14 +9,14 @@ use std::cmp;
 /// - Truncate the string to a specified maximum length, prioritizing complete words where possible.
 ///
 /// # Parameters
-/// - `input: &str`: The input string to normalize and truncate. This can include mixed characters, with the expectation
-///   that non-alphanumeric and non-whitespace characters will be removed.
-/// - `max_length: usize`: The maximum permissible length for the output string. The function ensures that the returned
-///   filename string does not exceed this length.
+/// - `input: &str`: The input string to normalize and truncate. This can include mixed characters,
+///   with the expectation that non-alphanumeric and non-whitespace characters will be removed.
+/// - `max_length: usize`: The maximum permissible length for the output string. The function
+///   ensures that the returned filename string does not exceed this length.
 ///
 /// # Returns
-/// - `String`: A string containing only alphanumeric characters and underscores, truncated as necessary to comply
-///   with the `max_length` constraint.
+/// - `String`: A string containing only alphanumeric characters and underscores, truncated as
+///   necessary to comply with the `max_length` constraint.
 pub fn normalized_filename_string(input: &str, max_length: usize) -> String {
     // Remove invalid characters and normalize to ASCII
     let normalized: String = input
@@ -69,7 +69,7 @@ pub fn normalized_filename_string(input: &str, max_length: usize) -> String {
 /// # Example
 ///
 /// ```rust
-/// use crate::utils::date_from_epoch_time;
+/// use herodote::utils::date_from_epoch_time;
 ///
 /// let epoch_time = 1638316800.0; // This represents December 1, 2021
 /// let date = date_from_epoch_time(epoch_time);
@@ -87,9 +87,24 @@ pub fn date_from_epoch_time(epoch_time: f64) -> String {
     datetime.format("%Y-%m-%d").to_string()
 }
 
+/// Converts an RFC 3339 timestamp, such as `2024-03-01T12:30:00.000000Z`, to epoch time.
+///
+/// # Arguments
+///
+/// * `timestamp` - A `&str` holding the RFC 3339 timestamp, with any UTC offset.
+///
+/// # Returns
+///
+/// * `Some(f64)` holding the number of seconds since the Unix epoch, including fractional
+///   seconds, or `None` if the timestamp cannot be parsed.
+pub fn epoch_time_from_rfc3339(timestamp: &str) -> Option<f64> {
+    let datetime = DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(datetime.timestamp() as f64 + datetime.timestamp_subsec_nanos() as f64 / 1_000_000_000.0)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::{date_from_epoch_time, normalized_filename_string};
+    use crate::utils::{date_from_epoch_time, epoch_time_from_rfc3339, normalized_filename_string};
 
     #[test]
     fn test_normalized_filename_string() {
@@ -123,4 +138,17 @@ mod tests {
             "1969-12-31"
         );
     }
+
+    #[test]
+    fn test_epoch_time_from_rfc3339() {
+        assert_eq!(
+            epoch_time_from_rfc3339("2023-01-01T00:00:00Z"),
+            Some(1672531200.0)
+        );
+        assert_eq!(
+            epoch_time_from_rfc3339("2023-01-01T01:00:00.500000+01:00"),
+            Some(1672531200.5)
+        );
+        assert_eq!(epoch_time_from_rfc3339("yesterday"), None);
+    }
 }